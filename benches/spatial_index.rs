@@ -0,0 +1,29 @@
+//Сравнение поиска еды и птичек по сетке с полным перебором на больших мирах
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use lib_simulation_lifelong::{Config, Simulation};
+
+fn step(c: &mut Criterion) {
+    let mut group = c.benchmark_group("step");
+    group.sample_size(20);
+
+    for (animals, foods) in [(200, 300), (1000, 1500), (4000, 6000)] {
+        for spatial_index in [false, true] {
+            let config = Config {
+                world_animals: animals,
+                world_foods: foods,
+                sim_spatial_index: spatial_index,
+                ..Default::default()
+            };
+            let mut sim = Simulation::from_seed(config, 1).unwrap();
+            let name = if spatial_index { "grid" } else { "brute_force" };
+
+            group.bench_with_input(BenchmarkId::new(name, animals), &animals, |b, _| {
+                b.iter(|| sim.step().unwrap())
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, step);
+criterion_main!(benches);
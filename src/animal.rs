@@ -1,9 +1,23 @@
 use std::ops::Deref;
 use crate::*;
 
+/// Постоянный номер птички, выдается миром World и не повторяется
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct AnimalId(pub(crate) u64);
+
+impl fmt::Display for AnimalId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
 #[derive(Debug)]
 pub struct Animal {//птичка
-    ///длительность жизни в эпохах (от 1 до 5 поколений, sim_generation_length/500)
+    ///постоянный номер птички
+    pub(crate) id: AnimalId,
+    ///номер вида птички в config.species_configs()
+    pub(crate) species: usize,
+    ///длительность жизни в эпохах (от sim_lifetime_min до sim_lifetime_max)
     ///уменьшается с каждой эпохой
     pub(crate) generation_lifetime: usize,
     ///количество прожитых эпох
@@ -15,20 +29,52 @@ pub struct Animal {//птичка
     ///обзор птички по еде (концентрация)
     pub(crate) vision_f: RefCell<Vec<f32>>,
     ///обзор птички по др. птичкам (номер наиболее концентрированной птички, концентрация)
-    pub(crate) vision_a: RefCell<Vec<(usize, f32)>>,
+    pub(crate) vision_a: RefCell<Vec<(Option<AnimalId>, f32)>>,
+    ///ближайшая птичка в каждом сегменте: (относительный курс, скорость, насыщенность)
+    pub(crate) vision_n: RefCell<Vec<[f32; 3]>>,
+    ///близость препятствий по сегментам (0 - нет, 1 - вплотную)
+    pub(crate) vision_o: RefCell<Vec<f32>>,
+    ///лучи (EyeMode::Rays): первое попадание каждого луча и расстояние до него
+    pub(crate) vision_r: RefCell<Vec<Option<(Hit, f32)>>>,
+    ///слух: сигналы соседей по сегментам (config.comm_channels каналов в каждом)
+    pub(crate) hearing: RefCell<Vec<f32>>,
+    ///сигнал, который птичка подает соседям (слышен на следующем шаге)
+    pub(crate) signal: Vec<f32>,
     ///скорость птички
     pub(crate) speed: f32,
     ///Зрение
     pub(crate) eye: Eye,
     ///Мышление
-    pub(crate) brain: FlexBrain,
+    pub(crate) brain: Box<dyn Brain>,
     ///насыщенность за эпоху птички едой (кол. съеденного)
     pub(crate) satiation: usize,
+    ///кол. шагов с последней съеденной еды
+    pub(crate) hunger: usize,
+    ///съела ли птичка еду на этом шаге (сбрасывается в process_energy)
+    pub(crate) ate: bool,
     ///насыщенность, приведенная к animal.generation_age
     pub(crate) satiation_avg: f32,
+    ///приспособленность для ГА (config.ga_fitness), приведенная к animal.generation_age
+    pub(crate) fitness_avg: f32,
+    ///энергия, с config.energy_enabled тратится каждый шаг и восполняется едой
+    pub(crate) energy: f32,
+    ///жива ли птичка (погибает, когда кончается энергия)
+    pub(crate) alive: bool,
+    ///кол. шагов, прожитых за эпоху
+    pub(crate) lived: usize,
+    ///кол. шагов, прожитых за всю жизнь
+    pub(crate) steps: usize,
 }
 
 impl Animal {
+    pub fn id(&self) -> AnimalId {
+        self.id
+    }
+
+    pub fn species(&self) -> usize {
+        self.species
+    }
+
     pub fn position(&self) -> na::Point2<f32> {
         self.position
     }
@@ -40,83 +86,246 @@ impl Animal {
     pub fn vision(&self) -> Vec<f32> {//для Web-страницы, для отображение сектора обзора птичек &[f32]
         self.vision_f.borrow().clone()
     }
+
+    pub fn vision_animals(&self) -> Vec<(Option<AnimalId>, f32)> {//ближайшие птички по сегментам
+        self.vision_a.borrow().clone()
+    }
+
+    pub fn vision_rays(&self) -> Vec<Option<(Hit, f32)>> {//для отображения лучей
+        self.vision_r.borrow().clone()
+    }
+
+    pub fn signal(&self) -> &[f32] {
+        &self.signal
+    }
+
+    pub fn energy(&self) -> f32 {
+        self.energy
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.alive
+    }
 }
 
 impl Animal {
-    pub(crate) fn random(config: &Config, rng: &mut dyn RngCore) -> Self {
-        let brain = FlexBrain::random(config, rng);
+    pub(crate) fn random(config: &Config, rng: &mut dyn RngCore, id: AnimalId) -> Self {
+        let brain = random_brain(config, rng);
 
-        Self::new(config, rng, brain)
+        Self::new(config, rng, brain, id)
     }
 
     pub(crate) fn from_chromosome(
         config: &Config,
         rng: &mut dyn RngCore,
         chromosomes: Vec<ga::Chromosome>,
-    ) -> Self {
-        let brain = FlexBrain::from_chromosome(config, chromosomes);
+        id: AnimalId,
+    ) -> Result<Self, BrainError> {
+        let brain = brain_from_chromosomes(config, chromosomes)?;
 
-        Self::new(config, rng, brain)
+        Ok(Self::new(config, rng, brain, id))
     }
     //выбранная по номеру сеть как хромосома
     pub(crate) fn as_chromosome(&self, nv_num: usize) -> ga::Chromosome {
         self.brain.as_chromosome(nv_num)
     }
-    //процесс видения еды и других птичек
-    pub(crate) fn process_vision(&self,
-                                 foods: &[Food],
-                                 animals: &[Animal],
-                                 cur: usize) -> (Vec<f32>, Vec<(usize, f32)>)
+    //все сети как хромосомы
+    pub(crate) fn as_chromosomes(&self) -> Vec<ga::Chromosome> {
+        self.brain.as_chromosomes()
+    }
+    //съедена еда питательностью nutrient
+    pub(crate) fn eat(&mut self, config: &Config, nutrient: f32) {
+        self.satiation += 1;//насыщенность за эпоху
+        self.hunger = 0;
+        self.ate = true;
+        if config.energy_enabled {
+            self.energy = (self.energy + config.energy_food * nutrient).min(config.energy_max);
+        }
+    }
+    //расход энергии за шаг: базовый, на скорость и на размер мозга
+    pub(crate) fn energy_cost(&self, config: &Config) -> f32 {
+        config.energy_decay
+            + config.energy_decay_speed * self.speed / config.sim_speed_max
+            + config.energy_decay_brain * self.brain.size() as f32
+    }
+    //приспособленность за прошедшую эпоху из steps шагов
+    pub(crate) fn epoch_fitness(&self, config: &Config, steps: usize) -> f32 {
+        let survival = self.lived as f32 / steps.max(1) as f32;
+        match config.ga_fitness {
+            FitnessMode::Satiation => self.satiation as f32,
+            FitnessMode::Survival => survival,
+            FitnessMode::SurvivalEnergy => survival + self.energy / config.energy_max,
+        }
+    }
+    //процесс видения еды и других птичек по сегментам. С лучами (EyeMode::Rays) сегменты
+    //не нужны, кроме ближайших птичек для канала eye_neighbours
+    pub(crate) fn process_vision<'a>(&self,
+                                     foods: impl IntoIterator<Item = &'a Food>,
+                                     animals: impl IntoIterator<Item = &'a Animal>,
+                                     config: &Config)
+        -> (Vec<f32>, Vec<(Option<AnimalId>, f32)>, Vec<[f32; 3]>)
     {
+        let sectors = config.eye_mode == EyeMode::Sectors;
         //смотрим на еду
-        let vision_f =
-            self.eye.process_vision_food(self.position, self.rotation, foods);
+        let vision_f = if sectors {
+            self.eye.process_vision_food(self.position, self.rotation, foods)
+        } else {
+            vec![0.0; self.eye.cells]
+        };
         //смотрим на других птичек
-        let vision_a =
-            self.eye.process_vision_animal(self.position, self.rotation, animals, cur);
-        (vision_f, vision_a)
+        let nearest = if sectors || config.eye_neighbours {
+            self.eye.process_vision_animal(self.position, self.rotation, animals, self.id)
+        } else {
+            vec![(None, 0.0); self.eye.cells]
+        };
+        let vision_a = nearest.iter().map(|&(a, con)| (a.map(|a| a.id), con)).collect();
+        let vision_n = nearest
+            .iter()
+            .map(|&(a, _)| a.map_or([0.0; 3], |a| self.neighbour(a, config)))
+            .collect();
+        (vision_f, vision_a, vision_n)
+    }
+    //процесс слушания сигналов других птичек
+    pub(crate) fn process_hearing<'a>(&self,
+                                      animals: impl IntoIterator<Item = &'a Animal>,
+                                      config: &Config) -> Vec<f32> {
+        self.eye.process_hearing(self.position,
+                                 self.rotation,
+                                 animals,
+                                 self.id,
+                                 config.comm_range,
+                                 config.comm_channels)
+    }
+    //признаки соседней птички: относительный курс (-1..1), скорость (0..1), насыщенность (0..1)
+    fn neighbour(&self, other: &Animal, config: &Config) -> [f32; 3] {
+        let heading = na::wrap(other.rotation.angle() - self.rotation.angle(), -PI, PI) / PI;
+        [heading, other.speed_level(config), other.satiation_level(config)]
+    }
+    //скорость 0..1 в диапазоне sim_speed_min..sim_speed_max
+    fn speed_level(&self, config: &Config) -> f32 {
+        let range = config.sim_speed_max - config.sim_speed_min;
+        if range > 0.0 {
+            (self.speed - config.sim_speed_min) / range
+        } else {
+            0.0
+        }
+    }
+    //насыщенность 0..1: энергия с energy_enabled, иначе по съеденному за эпоху
+    fn satiation_level(&self, config: &Config) -> f32 {
+        if config.energy_enabled {
+            self.energy / config.energy_max
+        } else {
+            1.0 - 1.0 / (1.0 + self.satiation as f32)
+        }
+    }
+    //чувства внутреннего состояния (config.sense_*), каждое 0..1 (поворот -1..1)
+    pub(crate) fn process_senses(&self, config: &Config) -> Vec<f32> {
+        let mut senses = Vec::with_capacity(config.sense_inputs());
+        if config.sense_speed {
+            senses.push(self.speed_level(config));
+        }
+        if config.sense_heading {
+            let angle = self.rotation.angle();
+            senses.extend([angle.sin(), angle.cos()]);
+        }
+        if config.sense_hunger {
+            senses.push((self.hunger as f32 / config.sim_epoch_length as f32).min(1.0));
+        }
+        if config.sense_satiation {
+            senses.push(self.satiation_level(config));
+        }
+        if config.sense_lifetime {
+            //в Cadence::Continuous отсчет по всей жизни, иначе по текущей эпохе
+            let lived = match config.sim_cadence {
+                Cadence::Continuous => self.steps,
+                _ => self.lived,
+            };
+            let lifespan = self.generation_lifetime * config.sim_epoch_length;
+            let longest = config.sim_lifetime_max * config.sim_epoch_length;
+            senses.push((lifespan.saturating_sub(lived) as f32 / longest as f32).min(1.0));
+        }
+        senses
     }
     //обдумывание коммуникации и перемещения
     pub(crate) fn process_brain(&mut self, config: &Config) {
-        let (vis_a_num, vis_a): (Vec<usize>, Vec<f32>) =
-            self.vision_a.borrow().clone().into_iter().unzip();
-        // //последовательная сеть размышлений и общения
-        // //обдумывание положения птичек и еды -> сообщения другим птичкам и намерений по
-        // //коррекции своего положения
-        // let (speed, rotation, msgs) =
-        //     self.brain.propagate_a(self.vision_f.borrow().clone(), vis_a);
-        // // обдумывание сообщений птичек и намерений по своему положению -> коррекция положения
-        // let (speed, rotation) = self.brain.propagate_m(msgs, speed, rotation);
-
-        let (speed, rotation) =
-            self.brain.propagate_0(self.vision_f.borrow().clone(), vis_a);
+        //вектор включенных каналов чувств
+        let mut vision: Vec<f32> = Vec::with_capacity(config.brain_inputs());
+        let sectors = config.eye_mode == EyeMode::Sectors;
+        if sectors && config.eye_food {
+            vision.extend(self.vision_f.borrow().iter());
+        }
+        if sectors && config.eye_animals {
+            vision.extend(self.vision_a.borrow().iter().map(|&(_, con)| con));
+        }
+        if !sectors {
+            //по каналу на вид попадания: близость (fov_range - расстояние) / fov_range
+            let rays = self.vision_r.borrow();
+            let range = config.eye_fov_range;
+            for kind in Hit::ALL {
+                vision.extend(rays.iter().map(|ray| match *ray {
+                    Some((hit, dist)) if hit == kind => (range - dist) / range,
+                    _ => 0.0,
+                }));
+            }
+        }
+        if config.eye_neighbours {
+            vision.extend(self.vision_n.borrow().iter().flatten());
+        }
+        if sectors && config.eye_obstacles {
+            vision.extend(self.vision_o.borrow().iter());
+        }
+        if config.comm_enabled {
+            vision.extend(self.hearing.borrow().iter());
+        }
+        vision.extend(self.process_senses(config));
+        let (speed, rotation, signal) = self.brain.propagate(vision);
+        self.signal = signal;
+        //обучение в течение жизни: подкрепление, если еда съедена на этом шаге
+        self.brain.learn(if self.ate { 1.0 } else { 0.0 });
 
         //преобразование приращения положения в итоговое
         self.speed = (self.speed + speed).clamp(config.sim_speed_min, config.sim_speed_max);
         self.rotation = na::Rotation2::new(self.rotation.angle() + rotation);
     }
-    //само перемещение
-    pub(crate) fn process_movement(&mut self) {
-        self.position += self.rotation * na::Vector2::new(0.0, self.speed);
-        self.position.x = na::wrap(self.position.x, 0.0, 1.0);
-        self.position.y = na::wrap(self.position.y, 0.0, 1.0);
+    //само перемещение, препятствия не пропускают птичку
+    pub(crate) fn process_movement(&mut self, topology: Topology, obstacles: &[Obstacle]) {
+        let step = self.rotation * na::Vector2::new(0.0, self.speed);
+        self.position += obstructed_step(self.position, step, obstacles, topology);
+        self.position = topology.confine(self.position);
     }
 }
 
 impl Animal {
-    fn new(config: &Config, rng: &mut dyn RngCore, brain: FlexBrain) -> Self {
+    fn new(config: &Config, rng: &mut dyn RngCore, brain: Box<dyn Brain>, id: AnimalId) -> Self {
         Self {
-            generation_lifetime: rng.gen_range(1..=config.sim_generation_length/500),//5
+            id,
+            species: 0,
+            generation_lifetime: rng.gen_range(config.sim_lifetime_min..=config.sim_lifetime_max),
             generation_age: 0,
-            position: rng.gen(),
+            //место вне препятствий есть всегда (Config::validate), ищем до попадания
+            position: std::iter::repeat_with(|| rng.gen())
+                .find(|&position| is_free(config, position, 0.0))
+                .unwrap(),
             rotation: rng.gen(),
             vision_f: RefCell::new(vec![0.0; config.eye_cells]),
-            vision_a: RefCell::new(vec![(0, 0.0); config.eye_cells]),
+            vision_a: RefCell::new(vec![(None, 0.0); config.eye_cells]),
+            vision_n: RefCell::new(vec![[0.0; 3]; config.eye_cells]),
+            vision_o: RefCell::new(vec![0.0; config.eye_cells]),
+            vision_r: RefCell::new(vec![None; config.eye_cells]),
+            hearing: RefCell::new(vec![0.0; config.eye_cells * config.brain_signals()]),
+            signal: vec![0.0; config.brain_signals()],
             speed: config.sim_speed_max,
             eye: Eye::new(config),
             brain,
             satiation: 0,
+            hunger: 0,
+            ate: false,
             satiation_avg: 0.0f32,
+            fitness_avg: 0.0f32,
+            energy: config.energy_max,
+            alive: true,
+            lived: 0,
+            steps: 0,
         }
     }
 }
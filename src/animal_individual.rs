@@ -1,8 +1,7 @@
-use lib_genetic_algorithm::Chromosome;
 use crate::*;
 #[derive(Clone, Debug)]
 pub struct AnimalIndividual {
-    fitness: f32,//насыщенность птички едой
+    fitness: f32,//приспособленность птички (config.ga_fitness)
     chromosome: ga::Chromosome,
     life_time: usize,//life_time сколько осталось жить птичке
     changed: bool,//замененная птичка
@@ -13,13 +12,20 @@ impl AnimalIndividual {
     //выбранная по номеру сеть как хромосома
     pub fn from_animal(animal: &Animal, nv_num: usize) -> Self {//из птички в хромосому
         Self {
-            fitness: animal.satiation_avg,
+            fitness: animal.fitness_avg,
             chromosome: animal.as_chromosome(nv_num),
             life_time: animal.generation_lifetime,
             changed: false,
             mut_force: 1,
         }
     }
+    //выбранная по номеру сеть с заданной приспособленностью (отбор вне эпохи ГА)
+    pub fn scored(animal: &Animal, nv_num: usize, fitness: f32) -> Self {
+        Self {
+            fitness,
+            ..Self::from_animal(animal, nv_num)
+        }
+    }
 }
 
 impl ga::Individual for AnimalIndividual {
@@ -45,7 +51,7 @@ impl ga::Individual for AnimalIndividual {
 }
 
 impl ga::IndividualFlex for AnimalIndividual {
-    fn create(chromosome: Chromosome, life_time: usize, changed: bool, mut_force: usize) -> Self  {
+    fn create(chromosome: ga::Chromosome, life_time: usize, changed: bool, mut_force: usize) -> Self  {
         Self {
             fitness: 0.0,
             chromosome,
@@ -64,4 +70,4 @@ impl ga::IndividualFlex for AnimalIndividual {
     fn mut_force(&self) -> usize {
         self.mut_force
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,163 @@
+//Обучение без интерфейса: simulation --config <файл|имя> --seed <n> --generations <n> --out <папка>
+use lib_simulation_lifelong::*;
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+const USAGE: &str = "\
+simulation [параметры]
+  --config <файл|имя>   конфигурация .json/.toml или встроенная (по умолчанию default)
+  --seed <n>            зерно генератора (по умолчанию случайное)
+  --generations <n>     кол. поколений (по умолчанию до Ctrl-C)
+  --out <папка>         папка для снимков и метрик (по умолчанию out)
+  --metrics <файл>      метрики поколений .csv или .jsonl в папке out (по умолчанию metrics.csv)
+  --snapshot-every <n>  снимок каждые n поколений (по умолчанию 10, 0 - только в конце)
+  --resume <файл>       продолжить со снимка вместо новой симуляции
+                        (конфигурация и генератор - из снимка, без --config и --seed)";
+
+struct Args {
+    config: Option<String>,
+    seed: Option<u64>,
+    generations: Option<usize>,
+    out: PathBuf,
+    metrics: String,
+    metrics_format: MetricsFormat,
+    snapshot_every: usize,
+    resume: Option<PathBuf>,
+}
+
+impl Args {
+    //None - запрошена справка (--help)
+    fn parse() -> Result<Option<Self>, String> {
+        let mut args = Self {
+            config: None,
+            seed: None,
+            generations: None,
+            out: PathBuf::from("out"),
+            metrics: "metrics.csv".to_string(),
+            metrics_format: MetricsFormat::Csv,
+            snapshot_every: 10,
+            resume: None,
+        };
+        let mut iter = std::env::args().skip(1);
+        while let Some(flag) = iter.next() {
+            if flag == "--help" || flag == "-h" {
+                return Ok(None);
+            }
+            let value = iter
+                .next()
+                .ok_or_else(|| format!("нет значения для {}\n{}", flag, USAGE))?;
+            let number = |value: &str| {
+                value
+                    .parse::<u64>()
+                    .map_err(|err| format!("{} {}: {}", flag, value, err))
+            };
+            match flag.as_str() {
+                "--config" => args.config = Some(value),
+                "--seed" => args.seed = Some(number(&value)?),
+                "--generations" => args.generations = Some(number(&value)? as usize),
+                "--out" => args.out = PathBuf::from(value),
+                "--metrics" => args.metrics = value,
+                "--snapshot-every" => args.snapshot_every = number(&value)? as usize,
+                "--resume" => args.resume = Some(PathBuf::from(value)),
+                _ => return Err(format!("неизвестный параметр {}\n{}", flag, USAGE)),
+            }
+        }
+        //снимок уже содержит конфигурацию и состояние генератора
+        if args.resume.is_some() && (args.config.is_some() || args.seed.is_some()) {
+            return Err(format!("--resume нельзя сочетать с --config и --seed\n{}", USAGE));
+        }
+        //формат проверяется до создания папки и симуляции
+        args.metrics_format = MetricsFormat::from_path(&args.metrics)
+            .ok_or_else(|| format!("неизвестный формат метрик {} (нужен .csv или .jsonl)", args.metrics))?;
+        Ok(Some(args))
+    }
+    //файл с расширением - путь, иначе имя встроенной конфигурации
+    fn config(&self) -> Result<Config, ConfigError> {
+        let config = self.config.as_deref().unwrap_or("default");
+        if PathBuf::from(config).extension().is_some() {
+            Config::from_path(config)
+        } else {
+            Config::preset(config)
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args = match Args::parse() {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(message) => {
+            eprintln!("{}", message);
+            return ExitCode::FAILURE;
+        }
+    };
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("ошибка: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Args) -> Result<(), Box<dyn std::error::Error>> {
+    let mut sim = match &args.resume {
+        Some(path) => Simulation::restore(Snapshot::load(path)?)?,
+        None => {
+            let config = args.config()?;
+            match args.seed {
+                Some(seed) => Simulation::from_seed(config, seed)?,
+                None => Simulation::random(config)?,
+            }
+        }
+    };
+    fs::create_dir_all(&args.out)?;
+
+    //Ctrl-C только поднимает флаг, симуляция останавливается между шагами
+    let stop = Arc::new(AtomicBool::new(false));
+    {
+        let stop = stop.clone();
+        ctrlc::set_handler(move || stop.store(true, Ordering::SeqCst))?;
+    }
+
+    let mut metrics = MetricsWriter::append_to(args.out.join(&args.metrics), args.metrics_format)?;
+
+    let mut done = 0;
+    while args.generations.map_or(true, |n| done < n) {
+        let Some(statistics) = train(&mut sim, &stop)? else {
+            break;
+        };
+        done += 1;
+        println!("{}", statistics);
+
+        metrics.append(&statistics)?;
+
+        if args.snapshot_every > 0 && done % args.snapshot_every == 0 {
+            let path = args.out.join(format!("snapshot-{:06}.bin", statistics.generation));
+            sim.snapshot().save(path, SnapshotFormat::Binary)?;
+        }
+    }
+
+    let path = args.out.join("final.bin");
+    sim.snapshot().save(&path, SnapshotFormat::Binary)?;
+    eprintln!("снимок сохранен: {}", path.display());
+    Ok(())
+}
+
+//одно поколение, None - если пришел Ctrl-C
+fn train(sim: &mut Simulation, stop: &AtomicBool) -> Result<Option<Statistics>, BrainError> {
+    loop {
+        if stop.load(Ordering::SeqCst) {
+            return Ok(None);
+        }
+        if let Some(statistics) = sim.step()? {
+            return Ok(Some(statistics));
+        }
+    }
+}
@@ -1,149 +1,150 @@
 use crate::*;
 
-#[derive(Debug)]
-pub struct Brain {
-    speed_accel: f32,
-    rotation_accel: f32,
-    nv: Vec<nn::SoftNetwork>,//0 - сеть намерения (intention), 1 - двигательная (motive) сеть
-}
-
-impl Brain {
-    //сеть с типовой топологией, но случайными весами
-    pub(crate) fn random(config: &Config, rng: &mut dyn RngCore) -> Self {
-        //сеть с типовой топологией, но случайными весами
-        let mut nv: Vec<nn::SoftNetwork> = Vec::new();
-        nv.push(nn::SoftNetwork::random(rng, &Self::topology_i(config)));
-        nv.push(nn::SoftNetwork::random(rng, &Self::topology_m(config)));
-        Self::new(config, nv)
+/// Мозг птички: по вектору чувств выдает приращения скорости и поворота и сигнал соседям.
+/// Гены мозга - хромосомы (по одной на нейросеть), которые эволюционирует ГА
+pub trait Brain: fmt::Debug {
+    /// Мозг с топологией из конфигурации, но случайными весами
+    fn random(config: &Config, rng: &mut dyn RngCore) -> Self
+    where
+        Self: Sized;
+    /// Мозг из хромосом (config.brain_networks() штук и хромосома пластичного слоя),
+    /// ошибка - если хромосомы не подходят к конфигурации (например, из чужого снимка)
+    fn from_chromosomes(config: &Config, chromosomes: Vec<ga::Chromosome>) -> Result<Self, BrainError>
+    where
+        Self: Sized;
+    /// Прямой проход: приращения скорости и поворота, сигнал (config.brain_signals())
+    fn propagate(&self, vision: Vec<f32>) -> (f32, f32, Vec<f32>);
+    /// Все нейросети как хромосомы
+    fn as_chromosomes(&self) -> Vec<ga::Chromosome>;
+    /// Выбранная по номеру нейросеть как хромосома
+    fn as_chromosome(&self, nv_num: usize) -> ga::Chromosome {
+        self.as_chromosomes().swap_remove(nv_num)
     }
-    pub(crate) fn from_chromosome(config: &Config,
-                                  chromosomes: Vec<ga::Chromosome>) -> Self {
-        let mut nv: Vec<nn::SoftNetwork> = Vec::new();
-        nv.push(nn::SoftNetwork::from_weights(chromosomes[0].iter()));
-        nv.push(nn::SoftNetwork::from_weights(chromosomes[1].iter()));
-        Self::new(config, nv)
+    /// Кол. весов (для расхода энергии на мозг)
+    fn size(&self) -> usize;
+    /// Кол. нейронов по слоям выбранной по номеру сети (для статистики)
+    fn neurons_by_layer(&self, _nv_num: usize) -> Vec<usize> {
+        Vec::new()
     }
-    //выбранная по номеру сеть как хромосома
-    pub(crate) fn as_chromosome(&self, nv_num: usize) -> ga::Chromosome {
-        self.nv[nv_num].weights().collect()
+    /// Память, переносимая между шагами (пустая у мозга без памяти)
+    fn memory(&self) -> Vec<f32> {
+        Vec::new()
     }
-    //сеть намерения (intention) - прямой проход
-    pub(crate) fn propagate_a(&self,
-                              vision_f: Vec<f32>,
-                              vision_a: Vec<f32>) -> (f32, f32, Vec<f32>) {
-        //соединяем 2 вектора
-        let vision: Vec<f32> = vision_f.iter().cloned().chain(vision_a.iter().cloned()).collect();
-        //расчет
-        let response = self.nv[0].propagate(vision);
-        //получаем первые 2 в один срез, а в другой срез - остальные элементы
-        let (resp1, resp2) = response.split_at(2);
-
-        let r0 = resp1[0].clamp(0.0, 1.0) - 0.5;
-        let r1 = resp1[1].clamp(0.0, 1.0) - 0.5;
-        let speed = (r0 + r1).clamp(-self.speed_accel, self.speed_accel);
-        let rotation = (r0 - r1).clamp(-self.rotation_accel, self.rotation_accel);
-
-        (speed, rotation, resp2.to_vec())
+    /// Восстановление памяти (из снимка)
+    fn set_memory(&self, _memory: Vec<f32>) {}
+    /// Обучение в течение жизни после прохода propagate (config.brain_plasticity),
+    /// modulation - поела ли птичка на этом шаге
+    fn learn(&self, _modulation: f32) {}
+    /// Выученные за жизнь веса (пустые у мозга без пластичности)
+    fn learned(&self) -> Vec<f32> {
+        Vec::new()
     }
-    //двигательная (motive) сеть - прямой проход
-    pub(crate) fn propagate_m(&self,
-                              msgs: Vec<f32>,
-                              speed: f32,
-                              rotation: f32) -> (f32, f32) {
-        let mut vision: Vec<f32> = Vec::from(msgs);
-        vision.push(speed);
-        vision.push(rotation);
-        let response = self.nv[1].propagate(vision);
-
-        let r0 = response[0].clamp(0.0, 1.0) - 0.5;
-        let r1 = response[1].clamp(0.0, 1.0) - 0.5;
-        let speed = (r0 + r1).clamp(-self.speed_accel, self.speed_accel);
-        let rotation = (r0 - r1).clamp(-self.rotation_accel, self.rotation_accel);
+    /// Восстановление выученных весов (из снимка)
+    fn set_learned(&self, _weights: Vec<f32>) {}
+}
 
-        (speed, rotation)
+/// Мозг выбранного в config.brain_kind вида со случайными весами
+pub(crate) fn random_brain(config: &Config, rng: &mut dyn RngCore) -> Box<dyn Brain> {
+    match config.brain_kind {
+        BrainKind::Flex => Box::new(FlexBrain::random(config, rng)),
+        BrainKind::Soft => Box::new(SoftBrain::random(config, rng)),
+        BrainKind::Scripted => Box::new(ScriptedBrain::random(config, rng)),
     }
+}
 
+/// Мозг выбранного в config.brain_kind вида из хромосом
+pub(crate) fn brain_from_chromosomes(config: &Config,
+                                     chromosomes: Vec<ga::Chromosome>)
+    -> Result<Box<dyn Brain>, BrainError>
+{
+    Ok(match config.brain_kind {
+        BrainKind::Flex => Box::new(FlexBrain::from_chromosomes(config, chromosomes)?),
+        BrainKind::Soft => Box::new(SoftBrain::from_chromosomes(config, chromosomes)?),
+        BrainKind::Scripted => Box::new(ScriptedBrain::from_chromosomes(config, chromosomes)?),
+    })
 }
 
-impl Brain {
-    fn new(config: &Config, nv: Vec<nn::SoftNetwork>) -> Self {
-        Self {
-            speed_accel: config.sim_speed_accel,
-            rotation_accel: config.sim_rotation_accel,
-            nv,//0 - сеть намерения (intention), 1 - двигательная (motive) сеть
-        }
-    }
-    //типовая сеть намерения (intention)
-    fn topology_i(config: &Config) -> [nn::SoftLayerTopology; 3] {
-        let mut connections1: Vec<(usize, usize)> = Vec::new();
-        // for i in 1..=9 { // config.eye_cells = 9
-        for i in 1..=18 { // config.eye_cells = 9 * 2 вектора
-            connections1.push((i, i));
-        };
-        let mut connections2: Vec<(usize, usize)> = Vec::new();
-        // for i in 10..=19 { // config.brain_neurons = 9
-        //      for j in 1..=9 {
-        for i in 19..=37 { // config.brain_neurons = 9 * 2 вектора
-            for j in 1..=18 {
-                connections2.push((i, j));
+/// Ошибка создания мозга из хромосом
+#[derive(Clone, Debug, PartialEq)]
+pub enum BrainError {
+    /// кол. хромосом не совпадает с кол. сетей мозга (и пластичного слоя)
+    Chromosomes { found: usize, expected: usize },
+    /// входной слой сети nv_num не совпадает с конфигурацией (чувства и память)
+    Inputs { nv_num: usize, found: usize, expected: usize },
+    /// кол. весов сети nv_num (SoftBrain) не совпадает с топологией из конфигурации
+    Weights { nv_num: usize, found: usize, expected: usize },
+    /// кол. генов пластичного слоя (веса и скорости обучения) не делится на строки по выходам
+    Plastic { found: usize, outputs: usize },
+}
+
+impl fmt::Display for BrainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Chromosomes { found, expected } => {
+                write!(f, "хромосом мозга {}, ожидается {}", found, expected)
             }
-        };
-        let mut connections3: Vec<(usize, usize)> = Vec::new();
-        // for i in 20..=21 { // 2
-        //     for j in 10..=19 {
-        for i in 38..=48 { // 2 + 9
-            for j in 19..=37 {
-                connections3.push((i, j));
+            Self::Inputs { nv_num, found, expected } => {
+                write!(f, "входов сети {} из хромосомы {}, ожидается {}", nv_num, found, expected)
             }
-        };
-
-        [
-            nn::SoftLayerTopology {
-                neurons: config.eye_cells * 2,//=9 * 2 вектора
-                connections: connections1,
-            },
-            nn::SoftLayerTopology {
-                neurons: config.brain_neurons * 2,//=9 * 2 вектора
-                connections: connections2,
-            },
-            nn::SoftLayerTopology {
-                neurons: 2 + 9,
-                connections: connections3,
-            },
-        ]
-    }
-    //типовая двигательная (motive) сеть
-    fn topology_m(config: &Config) -> [nn::SoftLayerTopology; 3] {
-        let mut connections1: Vec<(usize, usize)> = Vec::new();
-        for i in 1..=11 { // config.eye_cells = 9 + 2 (speed, rotation)
-            connections1.push((i, i));
-        };
-        let mut connections2: Vec<(usize, usize)> = Vec::new();
-        for i in 12..=23 { // config.brain_neurons = 9 + 2 (speed, rotation)
-            for j in 1..=11 {
-                connections2.push((i, j));
+            Self::Weights { nv_num, found, expected } => {
+                write!(f, "весов сети {} из хромосомы {}, ожидается {}", nv_num, found, expected)
             }
-        };
-        let mut connections3: Vec<(usize, usize)> = Vec::new();
-        for i in 24..=25 { // 2
-            for j in 12..=23 {
-                connections3.push((i, j));
+            Self::Plastic { found, outputs } => {
+                write!(f, "генов пластичного слоя {}, ожидается кратное 2 * {} выходов", found, outputs)
             }
-        };
+        }
+    }
+}
+
+impl std::error::Error for BrainError {}
+
+/// Первые 2 выхода нейросети -> приращения скорости и поворота
+pub(crate) fn brain_motion(response: &[f32], speed_accel: f32, rotation_accel: f32) -> (f32, f32) {
+    let r0 = response[0].clamp(0.0, 1.0) - 0.5;
+    let r1 = response[1].clamp(0.0, 1.0) - 0.5;
+    let speed = (r0 + r1).clamp(-speed_accel, speed_accel);
+    let rotation = (r0 - r1).clamp(-rotation_accel, rotation_accel);
+
+    (speed, rotation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_chacha::ChaCha8Rng;
+    use test_case::test_case;
+
+    #[test_case(BrainKind::Flex, 1; "flex")]
+    #[test_case(BrainKind::Soft, 1; "soft")]
+    #[test_case(BrainKind::Scripted, 0; "scripted")]
+    fn every_kind_roundtrips_chromosomes(brain_kind: BrainKind, networks: usize) {
+        let config = Config { brain_kind, comm_enabled: true, ..Default::default() };
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let brain = random_brain(&config, &mut rng);
+        assert_eq!(brain.as_chromosomes().len(), networks);
 
-        [
-            nn::SoftLayerTopology {
-                neurons: config.eye_cells + 2, //=9
-                connections: connections1,
-            },
-            nn::SoftLayerTopology {
-                neurons: config.brain_neurons + 2, //=9
-                connections: connections2,
-            },
-            nn::SoftLayerTopology {
-                neurons: 2,
-                connections: connections3,
-            },
-        ]
+        let vision = vec![0.5; config.brain_inputs()];
+        let (speed, rotation, signal) = brain.propagate(vision.clone());
+        assert!(speed.abs() <= config.sim_speed_accel);
+        assert!(rotation.abs() <= config.sim_rotation_accel);
+        assert_eq!(signal.len(), config.brain_signals());
+        //из своих хромосом мозг получается тем же
+        let copy = brain_from_chromosomes(&config, brain.as_chromosomes()).unwrap();
+        assert_eq!(copy.propagate(vision), (speed, rotation, signal));
     }
-}
\ No newline at end of file
+
+    #[test_case(BrainKind::Flex; "flex")]
+    #[test_case(BrainKind::Soft; "soft")]
+    fn rejects_other_inputs(brain_kind: BrainKind) {
+        let config = Config { brain_kind, ..Default::default() };
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let brain = random_brain(&config, &mut rng);
+        //те же сети, но у конфигурации другое кол. сегментов зрения
+        let other = Config { eye_cells: 5, ..config };
+        assert!(brain_from_chromosomes(&other, brain.as_chromosomes()).is_err());
+        assert_eq!(
+            brain_from_chromosomes(&other, Vec::new()).unwrap_err(),
+            BrainError::Chromosomes { found: 0, expected: 1 }
+        );
+    }
+}
@@ -1,53 +1,930 @@
 use crate::*;
+use std::path::Path;
 
+/// Конфигурация симуляции. В файле можно указать только часть полей,
+/// остальные берутся из Default
 #[derive(Clone, Debug, Serialize, Deserialize)]
-#[serde(deny_unknown_fields)]
+#[serde(default, deny_unknown_fields)]
 pub struct Config {
-    pub brain_neurons: usize,// размер 2-го слоя нейросети
+    pub brain_kind: BrainKind,// вид мозга птичек
+    pub brain_hidden: Vec<BrainLayer>,// скрытые слои нейросети (между входами и 2 выходами)
+    pub brain_output_activation: BrainActivation,// функция активации выходного слоя
+    pub brain_motive: bool,// вторая, двигательная (motive) сеть после сети намерения
+    pub brain_intentions: usize,// кол. намерений - выходов сети намерения для двигательной сети
+    pub brain_motive_hidden: Vec<BrainLayer>,// скрытые слои двигательной сети
+    pub brain_memory: usize,// ячейки памяти FlexBrain: доп. выходы сети, на след. шаге - её входы
+    pub brain_plasticity: Plasticity,// правило обучения пластичного слоя FlexBrain в течение жизни
+    pub brain_inheritance: Inheritance,// потомкам передаются начальные или выученные веса слоя
 
+    pub eye_mode: EyeMode,// сегменты с концентрацией или лучи
     pub eye_fov_range: f32,// дистанция видения
     pub eye_fov_angle: f32,// угол обзора
     pub eye_cells: usize,// кол. сегментов зрения
+    pub eye_food: bool,// канал зрения по еде
+    pub eye_animals: bool,// канал зрения по птичкам
+    pub eye_neighbours: bool,// канал курса, скорости и насыщенности ближайшей птички в сегменте
+    pub eye_obstacles: bool,// канал близости препятствий в сегменте
+
+    pub sense_speed: bool,// своя скорость, приведенная к sim_speed_min..sim_speed_max
+    pub sense_heading: bool,// синус и косинус своего поворота
+    pub sense_hunger: bool,// шагов с последней еды (доля sim_epoch_length, не больше 1)
+    pub sense_satiation: bool,// своя насыщенность (энергия с energy_enabled)
+    pub sense_lifetime: bool,// оставшееся время жизни (доля sim_lifetime_max эпох)
 
     pub food_size: f32,//размер еды для её захвата
+    pub food_nutrient: f32,//питательность свежей еды (множитель energy_food)
+    pub food_regrow_delay: usize,//средняя задержка роста новой еды в шагах (0 - сразу)
+    pub food_spoil_time: usize,//через сколько шагов еда портится (0 - не портится)
+    pub food_patches: usize,//кол. мест, вокруг которых растет еда (0 - по всему миру)
+    pub food_patch_radius: f32,//радиус места роста еды
+    pub food_patch_drift: f32,//скорость дрейфа мест роста еды за шаг
+    pub food_season_length: usize,//длина сезона в шагах: места по очереди богатеют (0 - без сезонов)
 
-    pub ga_reverse: usize,
+    pub comm_enabled: bool,// птички подают сигналы и слышат сигналы соседей
+    pub comm_channels: usize,// кол. каналов сигнала (доп. выходов нейросети)
+    pub comm_range: f32,// дальность слышимости сигнала
+
+    pub ga_selection: Selection,//отбор родителей
+    pub ga_tournament_size: usize,//кол. особей в турнире (Selection::Tournament)
+    pub ga_truncation: f32,//доля лучших особей для отбора (Selection::Truncation)
+    pub ga_crossover: Crossover,//скрещивание родителей
+    pub ga_elitism: usize,//кол. лучших птичек, которые не заменяются в эпоху эволюции
     pub ga_mut_chance: f32,//вероятность мутации 0.0..=1.0
     pub ga_mut_coeff: f32,//коэф.мутации: ген += ген * sign * коэф.мутации
+    pub ga_fitness: FitnessMode,//из чего считается приспособленность птички для ГА
 
     pub sim_speed_min: f32,
     pub sim_speed_max: f32,
     pub sim_speed_accel: f32,
     pub sim_rotation_accel: f32,
-    pub sim_generation_length: usize,//длительность 1-го цикла перед обучением
+    pub sim_epoch_length: usize,//длительность эпохи в шагах (между эволюциями и статистикой)
+    pub sim_lifetime_min: usize,//наименьшее время жизни новой птички в эпохах
+    pub sim_lifetime_max: usize,//наибольшее время жизни новой птички в эпохах
+    pub sim_cadence: Cadence,//когда происходит эволюция
+    pub sim_spatial_index: bool,//поиск еды и птичек по сетке вместо полного перебора
+
+    pub energy_enabled: bool,// птички тратят энергию и погибают от голода
+    pub energy_max: f32,// энергия при рождении и её предел
+    pub energy_food: f32,// энергия от одной съеденной еды
+    pub energy_decay: f32,// расход энергии за шаг
+    pub energy_decay_speed: f32,// доп. расход за шаг на скорости sim_speed_max
+    pub energy_decay_brain: f32,// доп. расход за шаг на каждый вес нейросети
+    pub energy_death: EnergyDeath,// что происходит с погибшей птичкой
 
     pub world_animals: usize,// кол. птичек на карте
     pub world_foods: usize,// кол. еды на карте
+    pub world_topology: Topology,// топология мира: тор, стены или плоскость
+    pub world_obstacles: Vec<Obstacle>,// неподвижные препятствия в единичном квадрате
+    pub species: Vec<Species>,// виды птичек со своими полями (пусто - один вид из world_animals)
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
-            brain_neurons: 9,
+            brain_kind: BrainKind::Flex,
+            brain_hidden: vec![BrainLayer { neurons: 2, activation: BrainActivation::Relu }],
+            brain_output_activation: BrainActivation::Relu,
+            brain_motive: false,
+            brain_intentions: 9,
+            brain_motive_hidden: vec![BrainLayer { neurons: 11, activation: BrainActivation::Relu }],
+            brain_memory: 0,
+            brain_plasticity: Plasticity::None,
+            brain_inheritance: Inheritance::Darwinian,
             //
+            eye_mode: EyeMode::Sectors,
             eye_fov_range: 0.25,
             eye_fov_angle: PI + FRAC_PI_4,
             eye_cells: 9,
+            eye_food: true,
+            eye_animals: true,
+            eye_neighbours: false,
+            eye_obstacles: false,
+            sense_speed: false,
+            sense_heading: false,
+            sense_hunger: false,
+            sense_satiation: false,
+            sense_lifetime: false,
             //
             food_size: 0.01,
+            food_nutrient: 1.0,
+            food_regrow_delay: 0,
+            food_spoil_time: 0,
+            food_patches: 0,
+            food_patch_radius: 0.1,
+            food_patch_drift: 0.0,
+            food_season_length: 0,
             //
-            ga_reverse: 0,
+            comm_enabled: false,
+            comm_channels: 2,
+            comm_range: 0.25,
+            //
+            ga_selection: Selection::Roulette,
+            ga_tournament_size: 3,
+            ga_truncation: 0.5,
+            ga_crossover: Crossover::Uniform,
+            ga_elitism: 0,
             ga_mut_chance: 0.01,
             ga_mut_coeff: 0.3,
+            ga_fitness: FitnessMode::Satiation,
             //
             sim_speed_min: 0.001,
             sim_speed_max: 0.005,
             sim_speed_accel: 0.2,
             sim_rotation_accel: FRAC_PI_2,
-            sim_generation_length: 2500,
+            sim_epoch_length: 625,
+            sim_lifetime_min: 1,
+            sim_lifetime_max: 5,
+            sim_cadence: Cadence::Interval,
+            sim_spatial_index: true,
+            //
+            energy_enabled: false,
+            energy_max: 1.0,
+            energy_food: 0.25,
+            energy_decay: 0.0005,
+            energy_decay_speed: 0.001,
+            energy_decay_brain: 0.000001,
+            energy_death: EnergyDeath::Respawn,
             //
             world_animals: 40,
             world_foods: 60,
+            world_topology: Topology::Torus,
+            world_obstacles: Vec::new(),
+            species: Vec::new(),
         }
     }
 }
+
+impl Config {
+    /// Имена встроенных конфигураций для (preset)
+    pub const PRESETS: &'static [&'static str] =
+        &["tiny-debug", "default", "large-world", "scarce-food", "food-patches", "predators",
+          "obstacles"];
+
+    /// Встроенная конфигурация по имени
+    pub fn preset(name: &str) -> Result<Self, ConfigError> {
+        let config = match name {
+            //маленький мир для отладки: мало птичек и еды, быстрые поколения, 3 сегмента зрения
+            "tiny-debug" => Self {
+                eye_cells: 3,
+                sim_epoch_length: 125,
+                sim_lifetime_max: 1,
+                world_animals: 6,
+                world_foods: 10,
+                ..Default::default()
+            },
+            "default" => Self::default(),
+            //много птичек и еды, зрение короче, чтобы сетка поиска оставалась мелкой
+            "large-world" => Self {
+                eye_fov_range: 0.1,
+                food_size: 0.005,
+                world_animals: 1000,
+                world_foods: 1500,
+                ..Default::default()
+            },
+            //еды меньше, чем птичек: важна стратегия поиска
+            "scarce-food" => Self {
+                world_foods: 10,
+                ..Default::default()
+            },
+            //еда растет у дрейфующих мест, которые по очереди богатеют, и портится
+            "food-patches" => Self {
+                food_regrow_delay: 20,
+                food_spoil_time: 1500,
+                food_patches: 3,
+                food_patch_drift: 0.0002,
+                food_season_length: 2000,
+                ..Default::default()
+            },
+            //добыча ест еду, быстрые хищники с узким и дальним зрением ловят добычу
+            "predators" => Self {
+                species: vec![
+                    Species { name: "prey".to_string(), animals: 36, ..Default::default() },
+                    Species {
+                        name: "hawk".to_string(),
+                        animals: 4,
+                        diet: Diet::Prey,
+                        sim_speed_max: Some(0.0065),
+                        eye_fov_range: Some(0.35),
+                        eye_fov_angle: Some(FRAC_PI_2),
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            },
+            //стены и камни, которые надо облетать; птички видят препятствия
+            "obstacles" => Self {
+                eye_obstacles: true,
+                world_topology: Topology::Walls,
+                world_obstacles: vec![
+                    Obstacle::Circle { center: [0.5, 0.5], radius: 0.1 },
+                    Obstacle::Box { min: [0.15, 0.7], max: [0.35, 0.75] },
+                    Obstacle::Polyline { points: vec![[0.7, 0.1], [0.7, 0.4], [0.9, 0.4]] },
+                ],
+                ..Default::default()
+            },
+            _ => return Err(ConfigError::UnknownPreset { name: name.to_string() }),
+        };
+        Ok(config)
+    }
+    /// Конфигурация из JSON или TOML файла (по расширению), поверх Default и с проверкой
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let name = path.display().to_string();
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| ConfigError::Io { path: name.clone(), message: err.to_string() })?;
+
+        let config = match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => Self::from_json_str(&text),
+            Some("toml") => Self::from_toml_str(&text),
+            _ => return Err(ConfigError::UnknownFormat { path: name }),
+        };
+        config.map_err(|err| match err {
+            ConfigError::Parse { message, .. } => ConfigError::Parse { path: name, message },
+            err => err,
+        })
+    }
+    /// Конфигурация из JSON поверх Default и с проверкой
+    pub fn from_json_str(json: &str) -> Result<Self, ConfigError> {
+        let config: Self = serde_json::from_str(json).map_err(|err| ConfigError::Parse {
+            path: String::new(),
+            message: err.to_string(),
+        })?;
+        config.validate()?;
+        Ok(config)
+    }
+    /// Конфигурация из TOML поверх Default и с проверкой
+    pub fn from_toml_str(text: &str) -> Result<Self, ConfigError> {
+        let config: Self = toml::from_str(text).map_err(|err| ConfigError::Parse {
+            path: String::new(),
+            message: err.to_string(),
+        })?;
+        config.validate()?;
+        Ok(config)
+    }
+    /// Кол. входов нейросети - сумма включенных каналов чувств
+    pub fn brain_inputs(&self) -> usize {
+        let vision = match self.eye_mode {
+            EyeMode::Sectors => {
+                self.eye_food as usize + self.eye_animals as usize + self.eye_obstacles as usize
+            }
+            //близость попадания луча в каждый вид объектов
+            EyeMode::Rays => Hit::ALL.len(),
+        };
+        let channels = vision
+            + 3 * self.eye_neighbours as usize
+            + self.brain_signals();
+        self.eye_cells * channels + self.sense_inputs()
+    }
+    /// Кол. входов внутреннего состояния (sense_*), идут после каналов сегментов
+    pub fn sense_inputs(&self) -> usize {
+        self.sense_speed as usize
+            + 2 * self.sense_heading as usize
+            + self.sense_hunger as usize
+            + self.sense_satiation as usize
+            + self.sense_lifetime as usize
+    }
+    /// Кол. входов двигательной сети: скорость и поворот, намерения, слух
+    pub fn motive_inputs(&self) -> usize {
+        2 + self.brain_intentions + self.hearing().len()
+    }
+    /// Место каналов слуха в векторе чувств: после каналов зрения, перед sense_*
+    pub fn hearing(&self) -> std::ops::Range<usize> {
+        let end = self.brain_inputs() - self.sense_inputs();
+        end - self.eye_cells * self.brain_signals()..end
+    }
+    /// Кол. нейросетей мозга (и хромосом птички), у ScriptedBrain генов нет
+    pub fn brain_networks(&self) -> usize {
+        match self.brain_kind {
+            BrainKind::Flex | BrainKind::Soft => 1 + self.brain_motive as usize,
+            BrainKind::Scripted => 0,
+        }
+    }
+    /// Есть ли у мозга пластичный слой (только у FlexBrain), его хромосома - после хромосом сетей
+    pub fn brain_plastic(&self) -> bool {
+        self.brain_kind == BrainKind::Flex && self.brain_plasticity != Plasticity::None
+    }
+    /// Кол. каналов сигнала (доп. выходов нейросети и каналов слуха в каждом сегменте)
+    pub fn brain_signals(&self) -> usize {
+        if self.comm_enabled {
+            self.comm_channels
+        } else {
+            0
+        }
+    }
+}
+
+/// Слой нейросети FlexBrain
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BrainLayer {
+    pub neurons: usize,
+    pub activation: BrainActivation,
+}
+
+/// Вид мозга птичек
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BrainKind {
+    /// FlexBrain - нейросети с эволюционирующей топологией
+    #[default]
+    Flex,
+    /// SoftBrain - нейросети nn::SoftNetwork
+    Soft,
+    /// ScriptedBrain - написанное вручную поведение для сравнения, без ГА
+    Scripted,
+}
+
+/// Функция активации слоя нейросети
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BrainActivation {
+    #[default]
+    Relu,
+    Relu6,
+    Sigmoid,
+    HardSigmoid,
+    Silu,
+}
+
+impl BrainActivation {
+    pub(crate) fn activation(self) -> Activation {
+        match self {
+            Self::Relu => Activation::Relu,
+            Self::Relu6 => Activation::Relu6,
+            Self::Sigmoid => Activation::Sigmoid,
+            Self::HardSigmoid => Activation::HardSigmoid,
+            Self::Silu => Activation::Silu,
+        }
+    }
+    /// Значение функции активации (для пластичного слоя вне nn)
+    pub(crate) fn apply(self, x: f32) -> f32 {
+        let sigmoid = |x: f32| 1.0 / (1.0 + (-x).exp());
+        match self {
+            Self::Relu => x.max(0.0),
+            Self::Relu6 => x.clamp(0.0, 6.0),
+            Self::Sigmoid => sigmoid(x),
+            Self::HardSigmoid => ((x + 3.0) / 6.0).clamp(0.0, 1.0),
+            Self::Silu => x * sigmoid(x),
+        }
+    }
+}
+
+/// Как птичка видит
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EyeMode {
+    /// в каждом из eye_cells сегментов - концентрация еды и птичек (eye_food, eye_animals)
+    #[default]
+    Sectors,
+    /// eye_cells лучей по углу обзора: расстояние до первого попадания и что попалось
+    /// (еда, птичка, препятствие, стена), ближнее загораживает дальнее
+    Rays,
+}
+
+/// Правило изменения весов пластичного слоя после каждого шага: вес связи += скорость
+/// обучения связи * изменение (вход связи x, выход слоя y)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Plasticity {
+    /// без пластичного слоя, веса не меняются в течение жизни
+    #[default]
+    None,
+    /// правило Хебба: y * x
+    Hebbian,
+    /// правило Ойи: y * (x - y * вес), веса не растут неограниченно
+    Oja,
+    /// правило Хебба, но только на шаге, когда птичка поела
+    Modulated,
+}
+
+/// Какие веса пластичного слоя наследуют потомки
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Inheritance {
+    /// по Дарвину: начальные веса из генов, выученное не наследуется
+    #[default]
+    Darwinian,
+    /// по Ламарку: выученные за жизнь веса становятся генами
+    Lamarckian,
+}
+
+/// Что происходит с птичкой, у которой кончилась энергия
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EnergyDeath {
+    /// сразу появляется новая птичка с мозгом живой птички (выбранной рулеткой по энергии)
+    #[default]
+    Respawn,
+    /// птичка выбывает до конца эпохи, затем возрождается из популяции ГА
+    Remove,
+}
+
+/// Отбор родителей в ГА
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Selection {
+    /// рулетка: вероятность пропорциональна приспособленности
+    #[default]
+    Roulette,
+    /// лучшая из ga_tournament_size случайных птичек
+    Tournament,
+    /// рулетка по рангу птички в популяции
+    Rank,
+    /// случайная птичка из доли ga_truncation лучших
+    Truncation,
+}
+
+/// Скрещивание родителей в ГА
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Crossover {
+    /// каждый ген от случайного родителя
+    #[default]
+    Uniform,
+    /// начало хромосомы от одного родителя, конец - от другого
+    SinglePoint,
+    /// без скрещивания: потомок - мутировавшая копия одного родителя
+    None,
+}
+
+/// Когда происходит эволюция
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Cadence {
+    /// вся популяция эволюционирует каждые sim_epoch_length шагов
+    #[default]
+    Interval,
+    /// худшая птичка заменяется потомком по одной, равномерно за эпоху;
+    /// каждые sim_epoch_length шагов - только статистика
+    SteadyState,
+    /// эволюция, когда погибли все птички (или прошло sim_epoch_length шагов)
+    Extinction,
+    /// когда птичка погибает от голода или кончается её время жизни (в шагах:
+    /// generation_lifetime * sim_epoch_length), сразу рождается один потомок двух живых
+    /// родителей рядом с родителем; каждые sim_epoch_length шагов - только статистика
+    Continuous,
+}
+
+/// Приспособленность птички для ГА (средняя за её возраст в эпохах)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FitnessMode {
+    /// кол. съеденной еды за эпоху
+    #[default]
+    Satiation,
+    /// доля эпохи, прожитая птичкой
+    Survival,
+    /// доля прожитой эпохи плюс оставшаяся энергия (доля energy_max)
+    SurvivalEnergy,
+}
+
+/// Ошибка проверки конфигурации
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConfigError {
+    /// неверный диапазон времени жизни птички в эпохах
+    LifetimeRange { min: usize, max: usize },
+    /// поле должно быть больше нуля
+    NotPositive { field: &'static str, value: f32 },
+    /// поле не может быть меньше нуля
+    Negative { field: &'static str, value: f32 },
+    /// счетчик должен быть больше нуля
+    Zero { field: &'static str },
+    /// sim_speed_min больше sim_speed_max или меньше нуля
+    SpeedRange { min: f32, max: f32 },
+    /// вероятность вне 0.0..=1.0
+    Probability { field: &'static str, value: f32 },
+    /// скрытый слой field[index] нейросети (brain_hidden или brain_motive_hidden) без нейронов
+    EmptyLayer { field: &'static str, index: usize },
+    /// все каналы чувств выключены, у нейросети нет входов
+    NoSensors,
+    /// препятствие world_obstacles[index] без размера
+    BadObstacle { index: usize },
+    /// препятствия закрывают весь мир, птичкам негде появиться
+    NoFreeSpace,
+    /// поле работает только с brain_kind = flex
+    FlexOnly { field: &'static str },
+    /// файл конфигурации не прочитан
+    Io { path: String, message: String },
+    /// расширение файла не .json и не .toml
+    UnknownFormat { path: String },
+    /// ошибка разбора JSON/TOML (неизвестное поле, неверный тип)
+    Parse { path: String, message: String },
+    /// нет встроенной конфигурации с таким именем
+    UnknownPreset { name: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LifetimeRange { min, max } => {
+                write!(f, "неверный диапазон времени жизни sim_lifetime_min = {} .. sim_lifetime_max = {}",
+                       min, max)
+            }
+            Self::NotPositive { field, value } => {
+                write!(f, "{} = {} должно быть больше нуля", field, value)
+            }
+            Self::Negative { field, value } => {
+                write!(f, "{} = {} не может быть меньше нуля", field, value)
+            }
+            Self::Zero { field } => write!(f, "{} должно быть больше нуля", field),
+            Self::SpeedRange { min, max } => {
+                write!(f, "неверный диапазон скорости sim_speed_min = {} .. sim_speed_max = {}", min, max)
+            }
+            Self::Probability { field, value } => {
+                write!(f, "{} = {} должно быть в диапазоне 0.0..=1.0", field, value)
+            }
+            Self::EmptyLayer { field, index } => write!(f, "скрытый слой {}[{}] без нейронов", field, index),
+            Self::NoSensors => {
+                write!(f, "все каналы чувств выключены \
+                           (eye_food, eye_animals, eye_neighbours, eye_obstacles, sense_*)")
+            }
+            Self::BadObstacle { index } => {
+                write!(f, "препятствие world_obstacles[{}] без размера или точек", index)
+            }
+            Self::NoFreeSpace => write!(f, "препятствия world_obstacles закрывают весь мир"),
+            Self::FlexOnly { field } => write!(f, "{} поддерживается только с brain_kind = flex", field),
+            Self::Io { path, message } => write!(f, "не удалось прочитать {}: {}", path, message),
+            Self::UnknownFormat { path } => {
+                write!(f, "неизвестный формат конфигурации {} (нужен .json или .toml)", path)
+            }
+            Self::Parse { path, message } => write!(f, "ошибка разбора {}: {}", path, message),
+            Self::UnknownPreset { name } => write!(
+                f,
+                "нет встроенной конфигурации {:?} (есть: {})",
+                name,
+                Config::PRESETS.join(", ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// Проверка конфигурации до создания мира
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.sim_epoch_length == 0 {
+            return Err(ConfigError::Zero { field: "sim_epoch_length" });
+        }
+        if self.sim_lifetime_min == 0 || self.sim_lifetime_min > self.sim_lifetime_max {
+            return Err(ConfigError::LifetimeRange {
+                min: self.sim_lifetime_min,
+                max: self.sim_lifetime_max,
+            });
+        }
+        for (field, value) in [
+            ("eye_fov_range", self.eye_fov_range),
+            ("eye_fov_angle", self.eye_fov_angle),
+            ("food_size", self.food_size),
+            ("food_nutrient", self.food_nutrient),
+            ("food_patch_radius", self.food_patch_radius),
+            ("sim_speed_max", self.sim_speed_max),
+            ("energy_max", self.energy_max),
+        ] {
+            if value.is_nan() || value <= 0.0 {
+                return Err(ConfigError::NotPositive { field, value });
+            }
+        }
+        //отрицательная еда или расход превращали бы голод в пополнение энергии
+        for (field, value) in [
+            ("energy_food", self.energy_food),
+            ("energy_decay", self.energy_decay),
+            ("energy_decay_speed", self.energy_decay_speed),
+            ("energy_decay_brain", self.energy_decay_brain),
+        ] {
+            if value.is_nan() || value < 0.0 {
+                return Err(ConfigError::Negative { field, value });
+            }
+        }
+        for (field, value) in [
+            ("eye_cells", self.eye_cells),
+            ("world_animals", self.world_animals),
+        ] {
+            if value == 0 {
+                return Err(ConfigError::Zero { field });
+            }
+        }
+        let (min, max) = (self.sim_speed_min, self.sim_speed_max);
+        if min.is_nan() || min < 0.0 || min > max {
+            return Err(ConfigError::SpeedRange {
+                min: self.sim_speed_min,
+                max: self.sim_speed_max,
+            });
+        }
+        if self.ga_selection == Selection::Tournament && self.ga_tournament_size == 0 {
+            return Err(ConfigError::Zero { field: "ga_tournament_size" });
+        }
+        if self.ga_selection == Selection::Truncation
+            && (self.ga_truncation.is_nan() || self.ga_truncation <= 0.0 || self.ga_truncation > 1.0)
+        {
+            return Err(ConfigError::Probability { field: "ga_truncation", value: self.ga_truncation });
+        }
+        if !(0.0..=1.0).contains(&self.ga_mut_chance) {
+            return Err(ConfigError::Probability {
+                field: "ga_mut_chance",
+                value: self.ga_mut_chance,
+            });
+        }
+        if self.comm_enabled {
+            if self.comm_channels == 0 {
+                return Err(ConfigError::Zero { field: "comm_channels" });
+            }
+            if self.comm_range.is_nan() || self.comm_range <= 0.0 {
+                return Err(ConfigError::NotPositive { field: "comm_range", value: self.comm_range });
+            }
+        }
+        if let Some(index) = self.brain_hidden.iter().position(|l| l.neurons == 0) {
+            return Err(ConfigError::EmptyLayer { field: "brain_hidden", index });
+        }
+        if self.brain_motive {
+            if self.brain_intentions == 0 {
+                return Err(ConfigError::Zero { field: "brain_intentions" });
+            }
+            if let Some(index) = self.brain_motive_hidden.iter().position(|l| l.neurons == 0) {
+                return Err(ConfigError::EmptyLayer { field: "brain_motive_hidden", index });
+            }
+        }
+        if self.brain_memory > 0 && self.brain_kind != BrainKind::Flex {
+            return Err(ConfigError::FlexOnly { field: "brain_memory" });
+        }
+        if self.brain_inputs() == 0 {
+            return Err(ConfigError::NoSensors);
+        }
+        if let Some(index) = self.world_obstacles.iter().position(|o| !o.is_valid()) {
+            return Err(ConfigError::BadObstacle { index });
+        }
+        if !self.world_obstacles.is_empty() && !has_free_space(self) {
+            return Err(ConfigError::NoFreeSpace);
+        }
+        //каждый вид проверяется как отдельная конфигурация
+        for species in &self.species {
+            if species.animals == 0 {
+                return Err(ConfigError::Zero { field: "species.animals" });
+            }
+            self.species_config(species).validate()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test]
+    fn default_is_valid() {
+        assert_eq!(Config::default().validate(), Ok(()));
+    }
+
+    #[test_case(
+        Config { sim_epoch_length: 0, ..Default::default() },
+        ConfigError::Zero { field: "sim_epoch_length" }
+        ; "empty epoch"
+    )]
+    #[test_case(
+        Config { sim_lifetime_min: 3, sim_lifetime_max: 2, ..Default::default() },
+        ConfigError::LifetimeRange { min: 3, max: 2 }
+        ; "lifetime range"
+    )]
+    #[test_case(
+        Config { eye_cells: 0, ..Default::default() },
+        ConfigError::Zero { field: "eye_cells" }
+        ; "zero eye cells"
+    )]
+    #[test_case(
+        Config { world_animals: 0, ..Default::default() },
+        ConfigError::Zero { field: "world_animals" }
+        ; "zero animals"
+    )]
+    #[test_case(
+        Config { energy_food: -0.1, ..Default::default() },
+        ConfigError::Negative { field: "energy_food", value: -0.1 }
+        ; "negative energy food"
+    )]
+    #[test_case(
+        Config { energy_decay: -0.1, ..Default::default() },
+        ConfigError::Negative { field: "energy_decay", value: -0.1 }
+        ; "negative energy decay"
+    )]
+    #[test_case(
+        Config { energy_decay_speed: -0.1, ..Default::default() },
+        ConfigError::Negative { field: "energy_decay_speed", value: -0.1 }
+        ; "negative speed decay"
+    )]
+    #[test_case(
+        Config { energy_decay_brain: -0.1, ..Default::default() },
+        ConfigError::Negative { field: "energy_decay_brain", value: -0.1 }
+        ; "negative brain decay"
+    )]
+    #[test_case(
+        Config { eye_fov_range: 0.0, ..Default::default() },
+        ConfigError::NotPositive { field: "eye_fov_range", value: 0.0 }
+        ; "zero fov range"
+    )]
+    #[test_case(
+        Config { eye_fov_angle: -1.0, ..Default::default() },
+        ConfigError::NotPositive { field: "eye_fov_angle", value: -1.0 }
+        ; "negative fov angle"
+    )]
+    #[test_case(
+        Config { food_size: 0.0, ..Default::default() },
+        ConfigError::NotPositive { field: "food_size", value: 0.0 }
+        ; "zero food size"
+    )]
+    #[test_case(
+        Config { sim_speed_min: 0.01, sim_speed_max: 0.005, ..Default::default() },
+        ConfigError::SpeedRange { min: 0.01, max: 0.005 }
+        ; "speed min above max"
+    )]
+    #[test_case(
+        Config { sim_speed_min: -0.001, ..Default::default() },
+        ConfigError::SpeedRange { min: -0.001, max: 0.005 }
+        ; "negative speed min"
+    )]
+    #[test_case(
+        Config { ga_mut_chance: 1.5, ..Default::default() },
+        ConfigError::Probability { field: "ga_mut_chance", value: 1.5 }
+        ; "mutation chance above one"
+    )]
+    #[test_case(
+        Config {
+            brain_hidden: vec![
+                BrainLayer { neurons: 3, activation: BrainActivation::Relu },
+                BrainLayer { neurons: 0, activation: BrainActivation::Relu },
+            ],
+            ..Default::default()
+        },
+        ConfigError::EmptyLayer { field: "brain_hidden", index: 1 }
+        ; "empty hidden layer"
+    )]
+    #[test_case(
+        Config {
+            brain_motive: true,
+            brain_motive_hidden: vec![BrainLayer { neurons: 0, activation: BrainActivation::Relu }],
+            ..Default::default()
+        },
+        ConfigError::EmptyLayer { field: "brain_motive_hidden", index: 0 }
+        ; "empty motive hidden layer"
+    )]
+    #[test_case(
+        Config { brain_motive: true, brain_intentions: 0, ..Default::default() },
+        ConfigError::Zero { field: "brain_intentions" }
+        ; "motive without intentions"
+    )]
+    #[test_case(
+        Config { ga_selection: Selection::Truncation, ga_truncation: 0.0, ..Default::default() },
+        ConfigError::Probability { field: "ga_truncation", value: 0.0 }
+        ; "empty truncation"
+    )]
+    #[test_case(
+        Config { comm_enabled: true, comm_channels: 0, ..Default::default() },
+        ConfigError::Zero { field: "comm_channels" }
+        ; "zero comm channels"
+    )]
+    #[test_case(
+        Config {
+            species: vec![Species { name: "empty".to_string(), ..Default::default() }],
+            ..Default::default()
+        },
+        ConfigError::Zero { field: "species.animals" }
+        ; "empty species"
+    )]
+    #[test_case(
+        Config {
+            species: vec![Species { animals: 3, eye_cells: Some(0), ..Default::default() }],
+            ..Default::default()
+        },
+        ConfigError::Zero { field: "eye_cells" }
+        ; "species without eye cells"
+    )]
+    #[test_case(
+        Config {
+            world_obstacles: vec![Obstacle::Polyline { points: vec![[0.5, 0.5]] }],
+            ..Default::default()
+        },
+        ConfigError::BadObstacle { index: 0 }
+        ; "polyline without segments"
+    )]
+    #[test_case(
+        Config { brain_kind: BrainKind::Soft, brain_memory: 2, ..Default::default() },
+        ConfigError::FlexOnly { field: "brain_memory" }
+        ; "memory of soft brain"
+    )]
+    #[test_case(
+        Config { brain_kind: BrainKind::Scripted, brain_memory: 2, ..Default::default() },
+        ConfigError::FlexOnly { field: "brain_memory" }
+        ; "memory of scripted brain"
+    )]
+    #[test_case(
+        Config {
+            world_obstacles: vec![Obstacle::Box { min: [0.0, 0.0], max: [1.0, 1.0] }],
+            ..Default::default()
+        },
+        ConfigError::NoFreeSpace
+        ; "obstacles cover the world"
+    )]
+    #[test_case(
+        Config { eye_food: false, eye_animals: false, ..Default::default() },
+        ConfigError::NoSensors
+        ; "no sensors"
+    )]
+    fn invalid(config: Config, expected: ConfigError) {
+        assert_eq!(config.validate(), Err(expected.clone()));
+        assert_eq!(Simulation::random(config).err(), Some(expected));
+    }
+
+    #[test]
+    fn presets_are_valid() {
+        for name in Config::PRESETS {
+            assert_eq!(Config::preset(name).unwrap().validate(), Ok(()), "{}", name);
+        }
+        assert!(matches!(
+            Config::preset("unknown"),
+            Err(ConfigError::UnknownPreset { .. })
+        ));
+    }
+
+    #[test]
+    fn partial_json_over_default() {
+        let config = Config::from_json_str(r#"{ "world_foods": 5, "world_topology": "walls" }"#)
+            .unwrap();
+
+        assert_eq!(config.world_foods, 5);
+        assert_eq!(config.world_topology, Topology::Walls);
+        assert_eq!(config.world_animals, Config::default().world_animals);
+    }
+
+    #[test]
+    fn partial_toml_over_default() {
+        let config = Config::from_toml_str(
+            r#"
+            eye_cells = 5
+
+            [[brain_hidden]]
+            neurons = 4
+            activation = "sigmoid"
+            "#,
+        )
+            .unwrap();
+
+        assert_eq!(config.eye_cells, 5);
+        assert_eq!(
+            config.brain_hidden,
+            vec![BrainLayer { neurons: 4, activation: BrainActivation::Sigmoid }]
+        );
+        assert_eq!(config.world_foods, Config::default().world_foods);
+    }
+
+    #[test]
+    fn rejects_unknown_and_invalid_fields() {
+        assert!(matches!(
+            Config::from_json_str(r#"{ "world_food": 5 }"#),
+            Err(ConfigError::Parse { .. })
+        ));
+        assert_eq!(
+            Config::from_toml_str("eye_cells = 0").err(),
+            Some(ConfigError::Zero { field: "eye_cells" })
+        );
+    }
+
+    #[test]
+    fn from_path() {
+        let dir = std::env::temp_dir().join(format!("simulation-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let json = dir.join("config.json");
+        let toml = dir.join("config.toml");
+        let yaml = dir.join("config.yaml");
+        std::fs::write(&json, r#"{ "world_animals": 7 }"#).unwrap();
+        std::fs::write(&toml, "world_animals = 8").unwrap();
+        std::fs::write(&yaml, "world_animals: 9").unwrap();
+
+        assert_eq!(Config::from_path(&json).unwrap().world_animals, 7);
+        assert_eq!(Config::from_path(&toml).unwrap().world_animals, 8);
+        assert!(matches!(Config::from_path(&yaml), Err(ConfigError::UnknownFormat { .. })));
+        assert!(matches!(
+            Config::from_path(dir.join("missing.json")),
+            Err(ConfigError::Io { .. })
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test_case(BrainActivation::Relu, -1.0, 0.0; "relu")]
+    #[test_case(BrainActivation::Relu6, 7.0, 6.0; "relu6")]
+    #[test_case(BrainActivation::Sigmoid, 0.0, 0.5; "sigmoid")]
+    #[test_case(BrainActivation::HardSigmoid, 1.5, 0.75; "hard sigmoid")]
+    #[test_case(BrainActivation::Silu, 0.0, 0.0; "silu")]
+    fn activation_value(activation: BrainActivation, x: f32, expected: f32) {
+        approx::assert_relative_eq!(activation.apply(x), expected);
+    }
+
+    #[test]
+    fn set_config_keeps_world_on_error() {
+        let mut sim = Simulation::from_seed(Default::default(), 0).unwrap();
+        let invalid = Config { eye_cells: 0, ..Default::default() };
+
+        assert!(sim.set_config(invalid).is_err());
+        assert_eq!(sim.config().eye_cells, Config::default().eye_cells);
+        assert_eq!(sim.world().animals().len(), Config::default().world_animals);
+    }
+}
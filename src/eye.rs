@@ -5,24 +5,48 @@ pub struct Eye {
     pub(crate) fov_range: f32,
     pub(crate) fov_angle: f32,
     pub(crate) cells: usize,
+    pub(crate) topology: Topology,
+    ///радиус еды и птичек для попадания луча (config.food_size)
+    pub(crate) target_radius: f32,
+}
+
+/// Во что попал луч зрения
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hit {
+    Food,
+    Animal,
+    Obstacle,
+    /// край квадрата (Topology::Walls)
+    Wall,
+}
+
+impl Hit {
+    /// Все виды попаданий в порядке каналов зрения
+    pub const ALL: [Hit; 4] = [Hit::Food, Hit::Animal, Hit::Obstacle, Hit::Wall];
 }
 
 impl Eye {
     pub(crate) fn new(config: &Config) -> Self {
-        Self::new_ex(config.eye_fov_range, config.eye_fov_angle, config.eye_cells)
+        Self {
+            target_radius: config.food_size,
+            ..Self::new_ex(config.eye_fov_range,
+                           config.eye_fov_angle,
+                           config.eye_cells,
+                           config.world_topology)
+        }
     }
     ///смотрим на еду
-    pub(crate) fn process_vision_food(
+    pub(crate) fn process_vision_food<'a>(
         &self,
         position: na::Point2<f32>,
         rotation: na::Rotation2<f32>,
-        foods: &[Food],
+        foods: impl IntoIterator<Item = &'a Food>,
     ) -> Vec<f32> {
         let mut cells = vec![0.0; self.cells];
 
         for food in foods {
             // входит ли расстояние от птички до цели в дистанцию
-            let vec = food.position - position;
+            let vec = self.topology.delta(position, food.position);
             let dist = vec.norm();
 
             if dist > self.fov_range {
@@ -47,22 +71,22 @@ impl Eye {
         cells
     }
     ///смотрим на других птичек
-    pub(crate) fn process_vision_animal(
+    pub(crate) fn process_vision_animal<'a>(
         &self,
         position: na::Point2<f32>,
         rotation: na::Rotation2<f32>,
-        animals: &[Animal],
-        cur: usize,
-    ) -> Vec<(usize, f32)> {
-        let mut cells = vec![(0, 0.0); self.cells];
+        animals: impl IntoIterator<Item = &'a Animal>,
+        cur: AnimalId,
+    ) -> Vec<(Option<&'a Animal>, f32)> {
+        // ближайшая птичка в сегменте и её концентрация
+        let mut cells: Vec<(Option<&'a Animal>, f32)> = vec![(None, 0.0); self.cells];
 
-        let mut i: usize = 0;// номер птички, которую видно в сегменте
         for animal in animals {
-            if i == cur {
+            if animal.id == cur {
                 continue;//саму себя исключаем
             }
             // входит ли расстояние от птички до цели в дистанцию
-            let vec = animal.position - position;
+            let vec = self.topology.delta(position, animal.position);
             let dist = vec.norm();
 
             if dist > self.fov_range {
@@ -84,18 +108,131 @@ impl Eye {
             let con = (self.fov_range - dist) / self.fov_range;
             // ближайшая птичка та, которая с большей концентрацией
             if con >= cells[cell].1 {
-                cells[cell].1 = con;
-                cells[cell].0 = i;
+                cells[cell] = (Some(animal), con);
+            }
+        }
+        cells
+    }
+    ///смотрим на препятствия: в каждом сегменте по 3 луча, близость ближайшего попадания
+    ///(fov_range - расстояние) / fov_range
+    pub(crate) fn process_vision_obstacles(
+        &self,
+        position: na::Point2<f32>,
+        rotation: na::Rotation2<f32>,
+        obstacles: &[Obstacle],
+    ) -> Vec<f32> {
+        let mut cells = vec![0.0; self.cells];
+        if obstacles.is_empty() {
+            return cells;
+        }
+        let width = self.fov_angle / self.cells as f32;
+        for (cell, con) in cells.iter_mut().enumerate() {
+            for part in [1.0 / 6.0, 0.5, 5.0 / 6.0] {
+                // направление луча (сегмент 0 - правый край сектора)
+                let angle = -self.fov_angle / 2.0 + (cell as f32 + part) * width;
+                let dir = na::Rotation2::new(rotation.angle() + angle) * na::Vector2::y();
+                for obstacle in obstacles {
+                    let origin = obstacle.local(position, self.topology);
+                    let Some(dist) = obstacle.ray(origin, dir) else {
+                        continue;
+                    };
+                    if dist <= self.fov_range {
+                        *con = f32::max(*con, (self.fov_range - dist) / self.fov_range);
+                    }
+                }
             }
-            i += 1;// следующая птичка
         }
         cells
     }
+    ///лучи: cells лучей по середине сегментов обзора, по каждому - первое попадание
+    ///и расстояние до него не дальше fov_range (ближнее загораживает дальнее)
+    pub(crate) fn process_vision_rays<'a>(
+        &self,
+        position: na::Point2<f32>,
+        rotation: na::Rotation2<f32>,
+        foods: impl IntoIterator<Item = &'a Food>,
+        animals: impl IntoIterator<Item = &'a Animal>,
+        cur: AnimalId,
+        obstacles: &[Obstacle],
+    ) -> Vec<Option<(Hit, f32)>> {
+        //еда и птички - круги радиуса target_radius (центры относительно птички)
+        let mut targets: Vec<(Hit, na::Vector2<f32>)> = foods
+            .into_iter()
+            .map(|f| (Hit::Food, self.topology.delta(position, f.position)))
+            .collect();
+        targets.extend(
+            animals
+                .into_iter()
+                .filter(|a| a.id != cur)//саму себя исключаем
+                .map(|a| (Hit::Animal, self.topology.delta(position, a.position))),
+        );
+        let reach = self.fov_range + self.target_radius;
+        targets.retain(|(_, vec)| vec.norm() <= reach);
+
+        let width = self.fov_angle / self.cells as f32;
+        (0..self.cells)
+            .map(|cell| {
+                // направление луча (луч 0 - правый край обзора)
+                let angle = -self.fov_angle / 2.0 + (cell as f32 + 0.5) * width;
+                let dir = na::Rotation2::new(rotation.angle() + angle) * na::Vector2::y();
+
+                let mut hits: Vec<(Hit, f32)> = targets
+                    .iter()
+                    .filter_map(|&(hit, vec)| {
+                        ray_circle(vec, self.target_radius, dir).map(|dist| (hit, dist))
+                    })
+                    .collect();
+                hits.extend(obstacles.iter().filter_map(|obstacle| {
+                    let origin = obstacle.local(position, self.topology);
+                    Some((Hit::Obstacle, obstacle.ray(origin, dir)?))
+                }));
+                hits.extend(self.ray_wall(position, dir).map(|dist| (Hit::Wall, dist)));
+                hits.into_iter()
+                    .filter(|&(_, dist)| dist <= self.fov_range)
+                    .min_by(|a, b| a.1.total_cmp(&b.1))
+            })
+            .collect()
+    }
+    ///слушаем сигналы других птичек: channels каналов в каждом из cells сегментов
+    ///по всему кругу (сегмент 0 начинается позади птички), громкость падает с расстоянием
+    pub(crate) fn process_hearing<'a>(
+        &self,
+        position: na::Point2<f32>,
+        rotation: na::Rotation2<f32>,
+        animals: impl IntoIterator<Item = &'a Animal>,
+        cur: AnimalId,
+        range: f32,
+        channels: usize,
+    ) -> Vec<f32> {
+        let mut cells = vec![0.0; self.cells * channels];
 
+        for animal in animals {
+            if animal.id == cur {
+                continue;//саму себя не слышим
+            }
+            let vec = self.topology.delta(position, animal.position);
+            let dist = vec.norm();
+
+            if dist > range {
+                continue;//слишком далеко
+            }
+            // какой сегмент круга слышит птичку
+            let angle = na::Rotation2::rotation_between(&na::Vector2::y(), &vec).angle();
+            let angle = na::wrap(angle - rotation.angle(), -PI, PI) + PI;
+            let cell = angle / (2.0 * PI) * (self.cells as f32);
+            let cell = (cell as usize).min(self.cells - 1);
+            // громкость сигнала
+            let loudness = (range - dist) / range;
+            for (c, s) in animal.signal.iter().take(channels).enumerate() {
+                cells[cell * channels + c] += loudness * s;
+            }
+        }
+        cells
+    }
 }
 
 impl Eye {
-    fn new_ex(fov_range: f32, fov_angle: f32, cells: usize) -> Self {
+    fn new_ex(fov_range: f32, fov_angle: f32, cells: usize, topology: Topology) -> Self {
         assert!(fov_range > 0.0);
         assert!(fov_angle > 0.0);
         assert!(cells > 0);
@@ -104,8 +241,23 @@ impl Eye {
             fov_range,
             fov_angle,
             cells,
+            topology,
+            target_radius: 0.0,
         }
     }
+    //расстояние вдоль луча до края квадрата (стены есть только у Topology::Walls)
+    fn ray_wall(&self, position: na::Point2<f32>, dir: na::Vector2<f32>) -> Option<f32> {
+        if self.topology != Topology::Walls {
+            return None;
+        }
+        (0..2)
+            .filter(|&axis| dir[axis] != 0.0)
+            .map(|axis| {
+                let edge = if dir[axis] > 0.0 { 1.0 } else { 0.0 };
+                ((edge - position[axis]) / dir[axis]).max(0.0)
+            })
+            .min_by(|a, b| a.total_cmp(b))
+    }
 }
 
 #[cfg(test)]
@@ -115,10 +267,16 @@ mod tests {
 
     const TEST_EYE_CELLS: usize = 13;
 
+    fn animal(config: &Config, id: u64, x: f32, y: f32) -> Animal {
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(id);
+        let mut animal = Animal::random(config, &mut rng, AnimalId(id));
+        animal.position = na::Point2::new(x, y);
+        animal.rotation = na::Rotation2::new(3.0 * FRAC_PI_2);
+        animal
+    }
+
     fn food(x: f32, y: f32) -> Food {
-        Food {
-            position: na::Point2::new(x, y),
-        }
+        Food::new(na::Point2::new(x, y))
     }
 
     struct TestCase {
@@ -128,12 +286,13 @@ mod tests {
         x: f32,
         y: f32,
         rot: f32,
+        topology: Topology,
         expected: &'static str,
     }
 
     impl TestCase {
         fn run(self) {
-            let eye = Eye::new_ex(self.fov_range, self.fov_angle, TEST_EYE_CELLS);
+            let eye = Eye::new_ex(self.fov_range, self.fov_angle, TEST_EYE_CELLS, self.topology);
 
             let actual = eye.process_vision_food(
                 na::Point2::new(self.x, self.y),
@@ -179,6 +338,7 @@ mod tests {
             y: 0.5,
             rot: 0.0,
             fov_range,
+            topology: Topology::Plane,
             expected,
         }
             .run()
@@ -209,6 +369,7 @@ mod tests {
             y: 0.5,
             rot: 3.0 * FRAC_PI_2,
             fov_angle,
+            topology: Topology::Plane,
             expected,
         }
             .run()
@@ -245,6 +406,7 @@ mod tests {
             rot: 3.0 * FRAC_PI_2,
             x,
             y,
+            topology: Topology::Plane,
             expected,
         }
             .run()
@@ -269,8 +431,245 @@ mod tests {
             x: 0.5,
             y: 0.5,
             rot,
+            topology: Topology::Plane,
             expected,
         }
             .run()
     }
+
+    #[test_case(Topology::Torus, "      +      ")]
+    #[test_case(Topology::Walls, "             ")]
+    #[test_case(Topology::Plane, "             ")]
+    fn across_the_edge(topology: Topology, expected: &'static str) {
+        TestCase {
+            foods: vec![food(0.0, 0.5)],
+            fov_range: 0.1,
+            fov_angle: FRAC_PI_2,
+            x: 0.95,
+            y: 0.5,
+            rot: 3.0 * FRAC_PI_2,
+            topology,
+            expected,
+        }
+            .run()
+    }
+
+    //лучи птички в (x, 0.5), смотрящей вдоль оси X, по 13 лучей на четверть круга:
+    //F - еда, A - птичка, O - препятствие, W - стена
+    struct RayTestCase {
+        foods: Vec<Food>,
+        animals: Vec<(f32, f32)>,
+        obstacles: Vec<Obstacle>,
+        fov_range: f32,
+        x: f32,
+        topology: Topology,
+        expected: &'static str,
+    }
+
+    impl RayTestCase {
+        fn run(self) {
+            let config = Config::default();
+            let mut eye = Eye::new_ex(self.fov_range, FRAC_PI_2, TEST_EYE_CELLS, self.topology);
+            eye.target_radius = 0.01;
+            let animals: Vec<Animal> = self
+                .animals
+                .iter()
+                .enumerate()
+                .map(|(id, &(x, y))| animal(&config, id as u64 + 1, x, y))
+                .collect();
+
+            let actual = eye.process_vision_rays(
+                na::Point2::new(self.x, 0.5),
+                na::Rotation2::new(3.0 * FRAC_PI_2),
+                &self.foods,
+                &animals,
+                AnimalId(0),
+                &self.obstacles,
+            );
+
+            let actual = actual
+                .into_iter()
+                .map(|ray| match ray {
+                    Some((Hit::Food, _)) => "F",
+                    Some((Hit::Animal, _)) => "A",
+                    Some((Hit::Obstacle, _)) => "O",
+                    Some((Hit::Wall, _)) => "W",
+                    None => " ",
+                })
+                .collect::<Vec<_>>()
+                .join("");
+
+            assert_eq!(actual, self.expected);
+        }
+    }
+
+    #[test_case(vec![], vec![], "             " ; "nothing")]
+    #[test_case(vec![food(0.8, 0.5)], vec![], "      F      " ; "food ahead")]
+    #[test_case(vec![food(0.8, 0.5)], vec![(0.7, 0.5)], "      A      " ; "animal hides food")]
+    #[test_case(vec![food(0.7, 0.5)], vec![(0.8, 0.5)], "      F      " ; "food hides animal")]
+    #[test_case(vec![food(0.2, 0.5)], vec![(0.5, 0.7)], "             " ; "behind and aside")]
+    fn rays_foods_and_animals(foods: Vec<Food>, animals: Vec<(f32, f32)>, expected: &'static str) {
+        RayTestCase {
+            foods,
+            animals,
+            obstacles: vec![],
+            fov_range: 1.0,
+            x: 0.5,
+            topology: Topology::Plane,
+            expected,
+        }
+            .run()
+    }
+
+    #[test_case(1.0, "    OOOOO    " ; "circle hides food")]
+    #[test_case(0.21, "     OOO     " ; "only nearest part in range")]
+    #[test_case(0.1, "             " ; "out of range")]
+    fn rays_obstacles(fov_range: f32, expected: &'static str) {
+        RayTestCase {
+            foods: vec![food(0.95, 0.5)],
+            animals: vec![],
+            obstacles: vec![Obstacle::Circle { center: [0.8, 0.5], radius: 0.1 }],
+            fov_range,
+            x: 0.5,
+            topology: Topology::Plane,
+            expected,
+        }
+            .run()
+    }
+
+    #[test_case(Topology::Walls, "WWWWWWWWWWWWW")]
+    #[test_case(Topology::Plane, "             ")]
+    #[test_case(Topology::Torus, "      F      ")]
+    fn rays_at_the_edge(topology: Topology, expected: &'static str) {
+        RayTestCase {
+            foods: vec![food(0.05, 0.5)],
+            animals: vec![],
+            obstacles: vec![],
+            fov_range: 0.5,
+            x: 0.9,
+            topology,
+            expected,
+        }
+            .run()
+    }
+
+    #[test]
+    fn ray_distance() {
+        let mut eye = Eye::new_ex(1.0, FRAC_PI_2, TEST_EYE_CELLS, Topology::Plane);
+        eye.target_radius = 0.01;
+        let rays = eye.process_vision_rays(
+            na::Point2::new(0.5, 0.5),
+            na::Rotation2::new(3.0 * FRAC_PI_2),
+            &[food(0.8, 0.5)],
+            &[],
+            AnimalId(0),
+            &[],
+        );
+        let (hit, dist) = rays[6].unwrap();
+        assert_eq!(hit, Hit::Food);
+        approx::assert_relative_eq!(dist, 0.29, epsilon = 1e-5);
+    }
+
+    #[test]
+    fn obstacle_proximity() {
+        let eye = Eye::new_ex(0.5, FRAC_PI_2, TEST_EYE_CELLS, Topology::Plane);
+        let wall = [Obstacle::Polyline { points: vec![[0.8, 0.0], [0.8, 1.0]] }];
+        let rotation = na::Rotation2::new(3.0 * FRAC_PI_2);
+        //стена впереди на расстоянии 0.3: ближе всего в середине обзора, по краям дальше
+        let actual = eye.process_vision_obstacles(na::Point2::new(0.5, 0.5), rotation, &wall);
+        approx::assert_relative_eq!(actual[6], 0.4, epsilon = 1e-4);
+        approx::assert_relative_eq!(actual[0], actual[12], epsilon = 1e-4);
+        assert!(actual.iter().all(|&con| con > 0.0 && con <= actual[6]));
+        //стена дальше fov_range
+        let actual = eye.process_vision_obstacles(na::Point2::new(0.1, 0.5), rotation, &wall);
+        assert!(actual.iter().all(|&con| con == 0.0));
+    }
+
+    #[test]
+    fn nearest_animal_ids() {
+        let config = Config::default();
+        let eye = Eye::new_ex(1.0, FRAC_PI_2, TEST_EYE_CELLS, Topology::Plane);
+        let animals = vec![
+            animal(&config, 10, 0.5, 0.5),//сама птичка
+            animal(&config, 11, 0.9, 0.5),
+            animal(&config, 12, 0.7, 0.5),//ближе, чем 11, в том же сегменте
+            animal(&config, 13, 1.0, 0.4),
+            animal(&config, 14, 1.0, 0.6),
+        ];
+
+        let actual = eye.process_vision_animal(
+            animals[0].position,
+            animals[0].rotation,
+            &animals,
+            AnimalId(10),
+        );
+        let ids: Vec<Option<u64>> = actual.iter().map(|(a, _)| a.map(|a| a.id.0)).collect();
+
+        let mut expected = vec![None; TEST_EYE_CELLS];
+        expected[4] = Some(14);
+        expected[6] = Some(12);
+        expected[8] = Some(13);
+        assert_eq!(ids, expected);
+    }
+
+    #[test]
+    fn hearing() {
+        let config = Config {
+            comm_enabled: true,
+            comm_channels: 2,
+            ..Default::default()
+        };
+        let eye = Eye::new_ex(1.0, FRAC_PI_2, 4, Topology::Plane);
+        let mut me = animal(&config, 0, 0.5, 0.5);
+        let mut front = animal(&config, 1, 0.75, 0.5);//впереди, на расстоянии 0.25
+        let mut behind = animal(&config, 2, 0.0, 0.5);//позади, на расстоянии 0.5
+        me.signal = vec![1.0, 1.0];
+        front.signal = vec![1.0, 0.0];
+        behind.signal = vec![0.0, 0.5];
+
+        let actual = eye.process_hearing(
+            me.position,
+            me.rotation,
+            [&me, &front, &behind],
+            me.id,
+            1.0,
+            2,
+        );
+        //4 сегмента по 2 канала: позади (0 и 3 - на границе), впереди (1 и 2 - на границе)
+        let front_cell = actual.chunks(2).position(|c| c[0] > 0.0).unwrap();
+        let behind_cell = actual.chunks(2).position(|c| c[1] > 0.0).unwrap();
+        assert!(front_cell == 1 || front_cell == 2);
+        assert!(behind_cell == 0 || behind_cell == 3);
+        approx::assert_relative_eq!(actual[front_cell * 2], 0.75);
+        approx::assert_relative_eq!(actual[behind_cell * 2 + 1], 0.25);
+        approx::assert_relative_eq!(actual.iter().sum::<f32>(), 1.0);
+    }
+
+    #[test]
+    fn neighbour_features() {
+        let config = Config {
+            eye_fov_range: 1.0,
+            world_topology: Topology::Plane,
+            eye_neighbours: true,
+            ..Default::default()
+        };
+        let me = animal(&config, 0, 0.5, 0.5);
+        let mut other = animal(&config, 1, 0.7, 0.5);
+        other.rotation = na::Rotation2::new(3.0 * FRAC_PI_2 + FRAC_PI_2);
+        other.speed = config.sim_speed_max;
+        other.satiation = 3;
+
+        let foods: Vec<Food> = Vec::new();
+        let (_, vision_a, vision_n) = me.process_vision(&foods, [&me, &other], &config);
+
+        let (cell, _) = vision_a
+            .iter()
+            .enumerate()
+            .find(|(_, (id, _))| *id == Some(AnimalId(1)))
+            .expect("соседняя птичка не видна");
+        approx::assert_relative_eq!(vision_n[cell][0], 0.5, epsilon = 1e-5);
+        approx::assert_relative_eq!(vision_n[cell][1], 1.0);
+        approx::assert_relative_eq!(vision_n[cell][2], 0.75);
+        assert_eq!(vision_a.iter().filter(|(id, _)| id.is_some()).count(), 1);
+    }
 }
@@ -2,227 +2,483 @@ use crate::*;
 
 #[derive(Debug)]
 pub struct FlexBrain {
+    inputs: usize,//кол. входов сети намерения
+    motive_inputs: usize,//кол. входов двигательной сети
+    hearing: std::ops::Range<usize>,//каналы слуха в векторе чувств (входы и двигательной сети)
+    intentions: usize,//кол. намерений, передаваемых от сети намерения двигательной сети
+    size: usize,//кол. весов всех сетей (для расхода энергии)
+    memory: RefCell<Vec<f32>>,//последние выходы сети намерения с прошлого шага (config.brain_memory)
+    plastic: Option<RefCell<PlasticLayer>>,//пластичный выходной слой сети намерения (config.brain_plasticity)
     speed_accel: f32,
     rotation_accel: f32,
     nv: Vec<nn::FlexNetwork>,//0 - сеть намерения (intention), 1 - двигательная (motive) сеть
 }
 
-impl FlexBrain {
+impl Brain for FlexBrain {
+    /// Brain из нейронных сетей с топологией из конфигурации, но случайными весами
+    fn random(config: &Config, rng: &mut dyn RngCore) -> Self {
+        //сети с топологией из конфигурации, но случайными весами
+        let mut nv: Vec<nn::FlexNetwork> = Vec::new();
+        nv.push(nn::FlexNetwork::random(rng, &Self::topology_0(config)));
+        if config.brain_motive {
+            nv.push(nn::FlexNetwork::random(rng, &Self::topology_m(config)));
+        }
+        let plastic = config.brain_plastic().then(|| PlasticLayer::random(config, rng));
+        Self::new(config, nv, plastic)
+    }
+    /// Brain из вектора хромосом каждой нейронной сети. Входной слой каждой сети
+    /// (топология в хромосоме) должен совпадать с конфигурацией
+    fn from_chromosomes(config: &Config, mut chromosomes: Vec<ga::Chromosome>) -> Result<Self, BrainError> {
+        let expected = config.brain_networks() + config.brain_plastic() as usize;
+        if chromosomes.len() != expected {
+            return Err(BrainError::Chromosomes { found: chromosomes.len(), expected });
+        }
+        let inputs = [config.brain_inputs() + config.brain_memory, config.motive_inputs()];
+        for (nv_num, &expected) in inputs.iter().take(config.brain_networks()).enumerate() {
+            let found = Self::layers_of(&chromosomes[nv_num]).first().copied().unwrap_or(0);
+            if found != expected {
+                return Err(BrainError::Inputs { nv_num, found, expected });
+            }
+        }
+        //последняя хромосома - пластичный слой: веса и скорости обучения по строке на выход
+        if let Some(c) = chromosomes.get(config.brain_networks()) {
+            let outputs = PlasticLayer::outputs(config);
+            if c.is_empty() || c.len() % (2 * outputs) != 0 {
+                return Err(BrainError::Plastic { found: c.len(), outputs });
+            }
+        }
+        let hidden = Self::layers_of(&chromosomes[0]).last().copied().unwrap_or(0);
+        let plastic = chromosomes
+            .split_off(config.brain_networks())
+            .pop()
+            .map(|c| PlasticLayer::from_chromosome(config, hidden, &c));
+        let nv: Vec<nn::FlexNetwork> = chromosomes
+            .iter()
+            .map(|c| nn::FlexNetwork::from_weights(c.iter()))
+            .collect();
+        Ok(Self::new(config, nv, plastic))
+    }
+    /// Прямой проход мозга: при наличии двигательной сети - последовательно
+    /// сеть намерения и двигательная сеть, иначе - единственная сеть
+    fn propagate(&self, vision: Vec<f32>) -> (f32, f32, Vec<f32>) {
+        if !self.has_motive() {
+            return self.propagate_0(vision);
+        }
+        let hearing = vision[self.hearing.clone()].to_vec();
+        //обдумывание положения птичек и еды -> сигнал другим птичкам и намерения по
+        //коррекции своего положения
+        let (speed, rotation, msgs, signal) = self.propagate_a(vision);
+        //обдумывание намерений по своему положению и сигналов соседей -> коррекция положения
+        let (speed, rotation) = self.propagate_m(msgs, &hearing, speed, rotation);
+
+        (speed, rotation, signal)
+    }
     /// Выбранная по номеру нейронная сеть как хромосома
-    pub(crate) fn as_chromosome(&self, nv_num: usize) -> ga::Chromosome {
+    fn as_chromosome(&self, nv_num: usize) -> ga::Chromosome {
         self.nv[nv_num].weights().collect()
     }
-    /// Сеть - прямой проход
-    pub(crate) fn propagate_0(&self,
-                              vision_f: Vec<f32>,
-                              vision_a: Vec<f32>) -> (f32, f32) {
-        //соединяем 2 вектора
-        let vision: Vec<f32> = vision_f.iter().cloned().chain(vision_a.iter().cloned()).collect();
+    /// Все нейронные сети как хромосомы (веса вместе с топологией), затем пластичный слой
+    fn as_chromosomes(&self) -> Vec<ga::Chromosome> {
+        let mut chromosomes: Vec<ga::Chromosome> =
+            (0..self.nv.len()).map(|n| self.as_chromosome(n)).collect();
+        chromosomes.extend(self.plastic.as_ref().map(|p| p.borrow().as_chromosome()));
+        chromosomes
+    }
+    /// Кол. весов всех сетей
+    fn size(&self) -> usize {
+        self.size
+    }
+    /// Кол. нейронов по слоям: топология сети могла измениться мутацией.
+    /// Выходной слой сети намерения с пластичностью - пластичный слой
+    fn neurons_by_layer(&self, nv_num: usize) -> Vec<usize> {
+        let mut layers = Self::layers_of(&self.as_chromosome(nv_num));
+        if let (0, Some(plastic)) = (nv_num, &self.plastic) {
+            layers.push(plastic.borrow().width());
+        }
+        layers
+    }
+    /// Память с прошлого шага
+    fn memory(&self) -> Vec<f32> {
+        self.memory.borrow().clone()
+    }
+    fn set_memory(&self, memory: Vec<f32>) {
+        assert_eq!(memory.len(), self.memory.borrow().len(), "размер памяти не совпадает");
+        *self.memory.borrow_mut() = memory;
+    }
+    /// Изменение весов выходного слоя сети намерения по последнему проходу
+    fn learn(&self, modulation: f32) {
+        if let Some(plastic) = &self.plastic {
+            plastic.borrow_mut().learn(modulation);
+        }
+    }
+    fn learned(&self) -> Vec<f32> {
+        self.plastic.as_ref().map_or_else(Vec::new, |p| p.borrow().weights().to_vec())
+    }
+    fn set_learned(&self, weights: Vec<f32>) {
+        if let Some(plastic) = &self.plastic {
+            plastic.borrow_mut().set_weights(weights);
+        }
+    }
+}
+
+impl FlexBrain {
+    /// Есть ли двигательная (motive) сеть
+    pub(crate) fn has_motive(&self) -> bool {
+        self.nv.len() > 1
+    }
+    /// Сеть - прямой проход: приращения скорости и поворота, сигнал другим птичкам
+    pub(crate) fn propagate_0(&self, vision: Vec<f32>) -> (f32, f32, Vec<f32>) {
         //расчет
-        let response = self.nv[0].propagate(vision);
-
-        let r0 = response[0].clamp(0.0, 1.0) - 0.5;
-        let r1 = response[1].clamp(0.0, 1.0) - 0.5;
-        let speed = (r0 + r1).clamp(-self.speed_accel, self.speed_accel);
-        let rotation = (r0 - r1).clamp(-self.rotation_accel, self.rotation_accel);
-
-        (speed, rotation)
-    }
-    // /// Сеть намерения (intention) - прямой проход
-    // pub(crate) fn propagate_a(&self,
-    //                           vision_f: Vec<f32>,
-    //                           vision_a: Vec<f32>) -> (f32, f32, Vec<f32>) {
-    //     //соединяем 2 вектора
-    //     let vision: Vec<f32> = vision_f.iter().cloned().chain(vision_a.iter().cloned()).collect();
-    //     //расчет
-    //     let response = self.nv[0].propagate(vision);
-    //     //получаем первые 2 в один срез, а в другой срез - остальные элементы
-    //     let (resp1, resp2) = response.split_at(2);
-    //
-    //     let r0 = resp1[0].clamp(0.0, 1.0) - 0.5;
-    //     let r1 = resp1[1].clamp(0.0, 1.0) - 0.5;
-    //     let speed = (r0 + r1).clamp(-self.speed_accel, self.speed_accel);
-    //     let rotation = (r0 - r1).clamp(-self.rotation_accel, self.rotation_accel);
-    //
-    //     (speed, rotation, resp2.to_vec())
-    // }
-    // /// Двигательная (motive) сеть - прямой проход
-    // pub(crate) fn propagate_m(&self,
-    //                           msgs: Vec<f32>,
-    //                           speed: f32,
-    //                           rotation: f32) -> (f32, f32) {
-    //     let mut vision: Vec<f32> = Vec::from(msgs);
-    //     vision.push(speed);
-    //     vision.push(rotation);
-    //     let response = self.nv[1].propagate(vision);
-    //
-    //     let r0 = response[0].clamp(0.0, 1.0) - 0.5;
-    //     let r1 = response[1].clamp(0.0, 1.0) - 0.5;
-    //     let speed = (r0 + r1).clamp(-self.speed_accel, self.speed_accel);
-    //     let rotation = (r0 - r1).clamp(-self.rotation_accel, self.rotation_accel);
-    //
-    //     (speed, rotation)
-    // }
+        let response = self.remember(vision);
+        let (speed, rotation) = brain_motion(&response, self.speed_accel, self.rotation_accel);
+        //остальные выходы - сигнал (config.comm_channels)
+        let signal = response[2..].iter().map(|s| s.clamp(0.0, 1.0)).collect();
+
+        (speed, rotation, signal)
+    }
+    /// Сеть намерения (intention) - прямой проход:
+    /// приращения скорости и поворота, намерения для двигательной сети, сигнал другим птичкам
+    pub(crate) fn propagate_a(&self, vision: Vec<f32>) -> (f32, f32, Vec<f32>, Vec<f32>) {
+        //расчет
+        let response = self.remember(vision);
+        let (speed, rotation) = brain_motion(&response, self.speed_accel, self.rotation_accel);
+        //получаем намерения в один срез, а в другой срез - сигнал
+        let (msgs, signal) = response[2..].split_at(self.intentions);
+        let signal = signal.iter().map(|s| s.clamp(0.0, 1.0)).collect();
+
+        (speed, rotation, msgs.to_vec(), signal)
+    }
+    /// Двигательная (motive) сеть - прямой проход: предложенные сетью намерения
+    /// приращения, намерения и слух -> итоговые приращения скорости и поворота
+    pub(crate) fn propagate_m(&self,
+                              msgs: Vec<f32>,
+                              hearing: &[f32],
+                              speed: f32,
+                              rotation: f32) -> (f32, f32) {
+        let mut vision: Vec<f32> = vec![speed, rotation];
+        vision.extend(msgs);
+        vision.extend_from_slice(hearing);
+        assert_eq!(vision.len(), self.motive_inputs, "вход не совпадает с двигательной сетью");
+        let response = self.nv[1].propagate(vision);
+
+        brain_motion(&response, self.speed_accel, self.rotation_accel)
+    }
+    /// Сеть намерения с памятью: к чувствам добавляется память прошлого шага,
+    /// последние выходы (0..1) запоминаются и отбрасываются из ответа.
+    /// С пластичностью выходной слой сети - пластичный слой
+    fn remember(&self, mut vision: Vec<f32>) -> Vec<f32> {
+        assert_eq!(vision.len(), self.inputs, "вектор чувств не совпадает с входами сети");
+        let mut memory = self.memory.borrow_mut();
+        vision.extend(memory.iter());
+        let mut response = self.nv[0].propagate(vision);
+        if let Some(plastic) = &self.plastic {
+            response = plastic.borrow_mut().propagate(&response);
+        }
+        let tail = response.len() - memory.len();
+        for (cell, output) in memory.iter_mut().zip(response.drain(tail..)) {
+            *cell = output.clamp(0.0, 1.0);
+        }
+        response
+    }
 }
 impl FlexBrain {
     /// Конструктор
-    fn new(config: &Config, nv: Vec<nn::FlexNetwork>) -> Self {
+    fn new(config: &Config, nv: Vec<nn::FlexNetwork>, plastic: Option<PlasticLayer>) -> Self {
+        let size = nv.iter().map(|n| n.weights().count()).sum::<usize>()
+            + plastic.as_ref().map_or(0, |p| p.size());
         Self {
+            inputs: config.brain_inputs(),
+            motive_inputs: config.motive_inputs(),
+            hearing: config.hearing(),
+            intentions: config.brain_intentions,
+            size,
+            memory: RefCell::new(vec![0.0; config.brain_memory]),//у новорожденной память пуста
             speed_accel: config.sim_speed_accel,
             rotation_accel: config.sim_rotation_accel,
             nv,//0 - сеть намерения (intention), 1 - двигательная (motive) сеть
+            plastic: plastic.map(RefCell::new),
         }
     }
-    /// Brain из нейронных сетей с типовой топологией, но случайными весами
-    pub(crate) fn random(config: &Config, rng: &mut dyn RngCore) -> Self {
-        //сеть с типовой топологией, но случайными весами
-        let mut nv: Vec<nn::FlexNetwork> = Vec::new();
-        nv.push(nn::FlexNetwork::random(rng, &Self::topology_0(config)));
-        // nv.push(nn::FlexNetwork::random(rng, &Self::topology_i(config)));
-        // nv.push(nn::FlexNetwork::random(rng, &Self::topology_m(config)));
-        Self::new(config, nv)
+    /// Сеть намерения (или единственная сеть): входной слой, скрытые слои config.brain_hidden
+    /// и выходы: скорость и поворот, намерения (если есть двигательная сеть), сигнал, память.
+    /// Последние входы - память, так веса обратных связей входят в хромосому.
+    /// С пластичностью выходного слоя в сети нет, его заменяет PlasticLayer
+    fn topology_0(config: &Config) -> Vec<nn::LayerTopologyFlex> {
+        let mut layers: Vec<(usize, BrainActivation)> = config
+            .brain_hidden
+            .iter()
+            .map(|l| (l.neurons, l.activation))
+            .collect();
+        if !config.brain_plastic() {
+            layers.push((PlasticLayer::outputs(config), config.brain_output_activation));
+        }
+        Self::topology(config.brain_inputs() + config.brain_memory, &layers)
     }
-    /// Brain из вектора хромосом каждой нейронной сети
-    pub(crate) fn from_chromosome(config: &Config,
-                                  chromosomes: Vec<ga::Chromosome>) -> Self {
-        let mut nv: Vec<nn::FlexNetwork> = Vec::new();
-        nv.push(nn::FlexNetwork::from_weights(chromosomes[0].iter()));
-        // nv.push(nn::FlexNetwork::from_weights(chromosomes[1].iter()));
-        Self::new(config, nv)
+    /// Двигательная сеть: входы - скорость и поворот, намерения, слух;
+    /// скрытые слои config.brain_motive_hidden; выходы - скорость и поворот
+    fn topology_m(config: &Config) -> Vec<nn::LayerTopologyFlex> {
+        let mut layers: Vec<(usize, BrainActivation)> = config
+            .brain_motive_hidden
+            .iter()
+            .map(|l| (l.neurons, l.activation))
+            .collect();
+        layers.push((2, config.brain_output_activation));
+        Self::topology(config.motive_inputs(), &layers)
     }
-    /// Типовая сеть
-    fn topology_0(config: &Config) -> [nn::LayerTopologyFlex; 3] {
+    /// Топология: входной слой передает каждый вход на свой нейрон, каждый следующий
+    /// слой (кол. нейронов, активация) полностью связан с предыдущим.
+    /// Нейроны нумеруются подряд с 1, вх.связь 0 - смещение (bias)
+    fn topology(inputs: usize,
+                layers: &[(usize, BrainActivation)]) -> Vec<nn::LayerTopologyFlex> {
         //входной слой
         //(вес,слой,нейрон,вх.связь)
-        let mut connections1: Vec<(f32, usize, usize, usize)> = Vec::new();
-        for i in 1..=18 { // config.eye_cells = 9 * 2 вектора
-            connections1.push((0.0,1,i,0));//bias
-            connections1.push((1.0,1,i,i));//weights
+        let mut connections: Vec<(f32, usize, usize, usize)> = Vec::new();
+        for i in 1..=inputs {
+            connections.push((0.0,1,i,0));//bias
+            connections.push((1.0,1,i,i));//weights
         };
-        //первый слой - 2 нейрона. 18 входов, 2 выхода
-        //(вес,слой,нейрон,вх.связь) 2
-        let mut connections2: Vec<(f32, usize, usize, usize)> = Vec::new();
-        for i in 19..=20 { // 2
-            connections2.push((0.0,2,i,0));//bias
-            for j in 1..=18 {
-                connections2.push((0.0,2,i,j));//weights
+        let mut topology = vec![nn::LayerTopologyFlex {
+            connections,
+            activation: Activation::Relu,
+        }];
+        //(первый, последний) нейрон предыдущего слоя
+        let mut prev = (1, inputs);
+        for (n, &(neurons, activation)) in layers.iter().enumerate() {
+            let layer = n + 2;
+            let (first, last) = (prev.1 + 1, prev.1 + neurons);
+            //(вес,слой,нейрон,вх.связь)
+            let mut connections: Vec<(f32, usize, usize, usize)> = Vec::new();
+            for i in first..=last {
+                connections.push((0.0,layer,i,0));//bias
+                for j in prev.0..=prev.1 {
+                    connections.push((0.0,layer,i,j));//weights
+                }
+            };
+            topology.push(nn::LayerTopologyFlex {
+                connections,
+                activation: activation.activation(),
+            });
+            prev = (first, last);
+        }
+        topology
+    }
+    /// Кол. нейронов по слоям из хромосомы сети: хромосома - связи (вес, слой, нейрон, вх.связь)
+    /// подряд, слои нумеруются с 1
+    pub(crate) fn layers_of(chromosome: &ga::Chromosome) -> Vec<usize> {
+        let genes: Vec<f32> = chromosome.iter().copied().collect();
+        let mut layers: Vec<Vec<usize>> = Vec::new();
+        for connection in genes.chunks_exact(4) {
+            let (layer, neuron) = (connection[1] as usize, connection[2] as usize);
+            if layer == 0 {
+                continue;
             }
-        };
-        //второй слой - 2 нейрона. 2 входа, 2 выхода
-        //(вес,слой,нейрон,вх.связь) 2
-        let mut connections3: Vec<(f32, usize, usize, usize)> = Vec::new();
-        for i in 21..=22 { // 2
-            connections3.push((0.0,3,i,0));//bias
-            for j in 19..=20 {
-                connections3.push((0.0,3,i,j));//weights
+            if layers.len() < layer {
+                layers.resize(layer, Vec::new());
+            }
+            if !layers[layer - 1].contains(&neuron) {
+                layers[layer - 1].push(neuron);
             }
+        }
+        layers.iter().map(|neurons| neurons.len()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_chacha::ChaCha8Rng;
+    use test_case::test_case;
+
+    //кол. нейронов входного слоя топологии
+    fn inputs_of(topology: &[nn::LayerTopologyFlex]) -> usize {
+        topology[0]
+            .connections
+            .iter()
+            .filter(|&&(_, _, _, input)| input != 0)
+            .count()
+    }
+
+    fn config() -> Config {
+        Config {
+            eye_cells: 5,
+            brain_hidden: vec![
+                BrainLayer { neurons: 4, activation: BrainActivation::Sigmoid },
+                BrainLayer { neurons: 3, activation: BrainActivation::Relu },
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn topology_from_config() {
+        let topology = FlexBrain::topology_0(&config());
+        //вход + 2 скрытых + выход
+        assert_eq!(topology.len(), 4);
+        assert_eq!(inputs_of(&topology), 10);
+        //нейроны нумеруются подряд: 1..=10, 11..=14, 15..=17, 18..=19
+        let neurons = |layer: &nn::LayerTopologyFlex| {
+            let mut ids: Vec<usize> = layer.connections.iter().map(|c| c.2).collect();
+            ids.dedup();
+            ids
         };
+        assert_eq!(neurons(&topology[1]), (11..=14).collect::<Vec<_>>());
+        assert_eq!(neurons(&topology[3]), vec![18, 19]);
+        //выходной нейрон связан со смещением и всеми нейронами предыдущего слоя
+        let inputs: Vec<usize> = topology[3]
+            .connections
+            .iter()
+            .filter(|c| c.2 == 18)
+            .map(|c| c.3)
+            .collect();
+        assert_eq!(inputs, vec![0, 15, 16, 17]);
+    }
+
+    #[test]
+    fn layers_from_chromosome() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let brain = FlexBrain::random(&config(), &mut rng);
+        assert_eq!(brain.neurons_by_layer(0), vec![10, 4, 3, 2]);
+    }
+
+    #[test]
+    fn default_topology_is_unchanged() {
+        let topology = FlexBrain::topology_0(&Config::default());
+        let sizes: Vec<usize> = topology.iter().map(|l| l.connections.len()).collect();
+        //18 входов по 2 связи, 2 нейрона по 19, 2 нейрона по 3
+        assert_eq!(sizes, vec![36, 38, 6]);
+    }
+
+    #[test]
+    fn propagates_enabled_channels() {
+        let config = Config {
+            eye_animals: false,
+            ..config()
+        };
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let brain = FlexBrain::random(&config, &mut rng);
+
+        brain.propagate_0(vec![0.5; 5]);
+    }
 
-        [
-            nn::LayerTopologyFlex {
-                connections: connections1,
-                activation: Activation::Relu,
-            },
-            nn::LayerTopologyFlex {
-                connections: connections2,
-                activation: Activation::Relu,
-            },
-            nn::LayerTopologyFlex {
-                connections: connections3,
-                activation: Activation::Relu,
-            },
-        ]
-    }
-    // /// Типовая сеть намерения (intention)
-    // fn topology_i(config: &Config) -> [nn::FlexLayerTopology; 3] {
-    //     //входной слой
-    //     //(вес,слой,нейрон,вх.связь)
-    //     let mut connections1: Vec<(f32, usize, usize, usize)> = Vec::new();
-    //     for i in 1..=18 { // config.eye_cells = 9 * 2 вектора
-    //         connections1.push((0.0,1,i,0));//bias
-    //         connections1.push((1.0,1,i,i));//weights
-    //     };
-    //     //первый слой - 18 нейронов. 18 входов, 18 выходов
-    //     //(вес,слой,нейрон,вх.связь) config.brain_neurons = 9 * 2 вектора
-    //     let mut connections2: Vec<(f32, usize, usize, usize)> = Vec::new();
-    //     for i in 19..=37 { // config.brain_neurons = 9 * 2 вектора
-    //         connections2.push((0.0,2,i,0));//bias
-    //         for j in 1..=18 {
-    //             connections2.push((0.0,2,i,j));//weights
-    //         }
-    //     };
-    //     //второй слой - 11 нейронов. 18 входов, 11 выходов
-    //     //(вес,слой,нейрон,вх.связь) 2 + 9
-    //     let mut connections3: Vec<(f32, usize, usize, usize)> = Vec::new();
-    //     for i in 38..=48 { // 2 + 9
-    //         connections3.push((0.0,3,i,0));//bias
-    //         for j in 19..=37 {
-    //             connections3.push((0.0,3,i,j));//weights
-    //         }
-    //     };
-    //
-    //     [
-    //         nn::FlexLayerTopology {
-    //             // neurons: config.eye_cells * 2,//=9 * 2 вектора
-    //             connections: connections1,
-    //             activation: Activation::Relu,
-    //         },
-    //         nn::FlexLayerTopology {
-    //             // neurons: config.brain_neurons * 2,//=9 * 2 вектора
-    //             connections: connections2,
-    //             activation: Activation::Relu,
-    //         },
-    //         nn::FlexLayerTopology {
-    //             // neurons: 2 + 9,
-    //             connections: connections3,
-    //             activation: Activation::Relu,
-    //         },
-    //     ]
-    // }
-    // /// Типовая двигательная (motive) сеть
-    // fn topology_m(config: &Config) -> [nn::FlexLayerTopology; 3] {
-    //     //входной слой
-    //     //(вес,слой,нейрон,вх.связь)
-    //     let mut connections1: Vec<(f32, usize, usize, usize)> = Vec::new();
-    //     for i in 1..=11 { // config.eye_cells = 9 + 2 (speed, rotation)
-    //         connections1.push((0.0,1,i,0));//bias
-    //         connections1.push((1.0,1,i,i));//weights
-    //     };
-    //     //первый слой - 11 нейронов. 11 входов, 11 выходов
-    //     //(вес,слой,нейрон,вх.связь) config.brain_neurons = 9 + 2 (speed, rotation)
-    //     let mut connections2: Vec<(f32, usize, usize, usize)> = Vec::new();
-    //     for i in 12..=23 { // config.brain_neurons = 9 + 2 (speed, rotation)
-    //         connections2.push((0.0,2,i,0));//bias
-    //         for j in 1..=11 {
-    //             connections2.push((0.0,2,i,j));//weights
-    //         }
-    //     };
-    //     //второй слой - 2 нейрона. 11 входов, 2 выхода
-    //     //(вес,слой,нейрон,вх.связь) 2 + 9
-    //     let mut connections3: Vec<(f32, usize, usize, usize)> = Vec::new();
-    //     for i in 24..=25 { // 2
-    //         connections3.push((0.0,3,i,0));//bias
-    //         for j in 12..=23 {
-    //             connections3.push((0.0,3,i,j));//weights
-    //         }
-    //     };
-    //
-    //     [
-    //         nn::FlexLayerTopology {
-    //             // neurons: config.eye_cells + 2, //=9 + 2
-    //             connections: connections1,
-    //             activation: Activation::Relu,
-    //         },
-    //         nn::FlexLayerTopology {
-    //             // neurons: config.brain_neurons + 2, //=9 + 2
-    //             connections: connections2,
-    //             activation: Activation::Relu,
-    //         },
-    //         nn::FlexLayerTopology {
-    //             // neurons: 2,
-    //             connections: connections3,
-    //             activation: Activation::Relu,
-    //         },
-    //     ]
-    // }
-}
\ No newline at end of file
+    #[test]
+    fn signal_outputs() {
+        let config = Config {
+            comm_enabled: true,
+            comm_channels: 3,
+            ..config()
+        };
+        let topology = FlexBrain::topology_0(&config);
+        //входы: еда, птички и слух по 5 сегментов, 3 канала в каждом
+        assert_eq!(inputs_of(&topology), 5 + 5 + 5 * 3);
+
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let brain = FlexBrain::random(&config, &mut rng);
+        let (_, _, signal) = brain.propagate_0(vec![0.5; 25]);
+        assert_eq!(signal.len(), 3);
+        assert!(signal.iter().all(|s| (0.0..=1.0).contains(s)));
+    }
+
+    #[test]
+    fn intention_and_motive() {
+        let config = Config {
+            brain_motive: true,
+            brain_intentions: 4,
+            comm_enabled: true,
+            comm_channels: 2,
+            ..config()
+        };
+        //5 сегментов: еда, птички, слух 2 канала
+        let topology_0 = FlexBrain::topology_0(&config);
+        assert_eq!(inputs_of(&topology_0), 20);
+        let outputs = topology_0.last().unwrap().connections.iter().filter(|c| c.3 == 0).count();
+        assert_eq!(outputs, 2 + 4 + 2);
+        //слух - и на входах двигательной сети
+        let topology_m = FlexBrain::topology_m(&config);
+        assert_eq!(inputs_of(&topology_m), 2 + 4 + 5 * 2);
+        assert_eq!(config.hearing(), 10..20);
+
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let brain = FlexBrain::random(&config, &mut rng);
+        assert!(brain.has_motive());
+        assert_eq!(brain.as_chromosomes().len(), 2);
+
+        let (speed, rotation, msgs, signal) = brain.propagate_a(vec![0.5; 20]);
+        assert_eq!((msgs.len(), signal.len()), (4, 2));
+        let (speed, rotation) = brain.propagate_m(msgs, &[0.5; 10], speed, rotation);
+        assert!(speed.abs() <= config.sim_speed_accel);
+        assert!(rotation.abs() <= config.sim_rotation_accel);
+    }
+
+    #[test]
+    fn memory_is_carried_and_reset_on_birth() {
+        let config = Config {
+            brain_memory: 3,
+            comm_enabled: true,
+            comm_channels: 2,
+            ..config()
+        };
+        let topology = FlexBrain::topology_0(&config);
+        //еда, птички, слух 2 канала по 5 сегментов и 3 ячейки памяти
+        assert_eq!(inputs_of(&topology), 20 + 3);
+        let outputs = topology.last().unwrap().connections.iter().filter(|c| c.3 == 0).count();
+        assert_eq!(outputs, 2 + 2 + 3);
+
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let brain = FlexBrain::random(&config, &mut rng);
+        assert_eq!(brain.memory(), vec![0.0; 3]);
+        let first = brain.propagate_0(vec![0.5; 20]);
+        assert_eq!(first.2.len(), 2);
+        let memory = brain.memory();
+        assert!(memory.iter().all(|m| (0.0..=1.0).contains(m)));
+        //из хромосом рождается мозг с пустой памятью, он повторяет первый шаг
+        let copy = FlexBrain::from_chromosomes(&config, brain.as_chromosomes()).unwrap();
+        assert_eq!(copy.memory(), vec![0.0; 3]);
+        assert_eq!(copy.propagate_0(vec![0.5; 20]), first);
+        //с памятью первого шага мозг повторяет второй шаг
+        let second = brain.propagate_0(vec![0.5; 20]);
+        let copy = FlexBrain::from_chromosomes(&config, brain.as_chromosomes()).unwrap();
+        copy.set_memory(memory);
+        assert_eq!(copy.propagate_0(vec![0.5; 20]), second);
+    }
+
+    #[test_case(Inheritance::Darwinian, false; "darwinian")]
+    #[test_case(Inheritance::Lamarckian, true; "lamarckian")]
+    fn plastic_layer_learns_and_is_inherited(brain_inheritance: Inheritance, learned: bool) {
+        let config = Config {
+            brain_motive: true,
+            brain_plasticity: Plasticity::Hebbian,
+            brain_inheritance,
+            ..config()
+        };
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let brain = FlexBrain::random(&config, &mut rng);
+        //сети намерения и двигательная, затем пластичный слой
+        let chromosomes = brain.as_chromosomes();
+        assert_eq!(chromosomes.len(), 3);
+        assert_eq!(chromosomes[2].len(), PlasticLayer::genes(&config));
+        //выходной слой сети намерения - пластичный
+        let outputs = 2 + config.brain_intentions;
+        assert_eq!(FlexBrain::layers_of(&chromosomes[0]), vec![10, 4, 3]);
+        assert_eq!(brain.neurons_by_layer(0), vec![10, 4, 3, outputs]);
+
+        let born = brain.learned();
+        for _ in 0..10 {
+            brain.propagate(vec![1.0; 10]);
+            brain.learn(0.0);
+        }
+        assert_ne!(brain.learned(), born);
+        //потомок получает выученные веса только по Ламарку
+        let child = FlexBrain::from_chromosomes(&config, brain.as_chromosomes()).unwrap();
+        assert_eq!(child.learned() == brain.learned(), learned);
+        assert_eq!(child.learned() == born, !learned);
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_mismatched_vision() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let brain = FlexBrain::random(&config(), &mut rng);
+
+        brain.propagate_0(vec![0.5; 18]);
+    }
+}
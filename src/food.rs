@@ -3,18 +3,290 @@ use crate::*;
 #[derive(Debug)]
 pub struct Food {
     pub(crate) position: na::Point2<f32>,
+    ///возраст еды в шагах (для порчи, config.food_spoil_time)
+    pub(crate) age: usize,
+    ///через сколько шагов еда вырастет снова (0 - еда есть)
+    pub(crate) regrow: usize,
 }
 
 impl Food {
     pub fn position(&self) -> na::Point2<f32> {
         self.position
     }
+
+    pub fn is_present(&self) -> bool {
+        self.regrow == 0
+    }
 }
 
 impl Food {
-    pub(crate) fn random(rng: &mut dyn RngCore) -> Self {
+    pub(crate) fn new(position: na::Point2<f32>) -> Self {
         Self {
-            position: rng.gen(),
+            position,
+            age: 0,
+            regrow: 0,
+        }
+    }
+    //новая еда вне препятствий, без места - вырастет позже
+    pub(crate) fn random(config: &Config, patches: &FoodPatches, rng: &mut dyn RngCore) -> Self {
+        let mut food = Self::new(na::Point2::origin());
+        food.grow(config, patches, rng);
+        food
+    }
+    //питательность с учетом порчи: линейно убывает до нуля к config.food_spoil_time
+    pub(crate) fn nutrient(&self, config: &Config) -> f32 {
+        if config.food_spoil_time == 0 {
+            return config.food_nutrient;
+        }
+        let freshness = 1.0 - self.age as f32 / config.food_spoil_time as f32;
+        config.food_nutrient * freshness.max(0.0)
+    }
+    //еда съедена или испортилась: сразу растет новая или через случайную задержку
+    pub(crate) fn consume(&mut self,
+                          config: &Config,
+                          patches: &FoodPatches,
+                          rng: &mut dyn RngCore) {
+        self.age = 0;
+        self.regrow = regrow_delay(config.food_regrow_delay, rng);
+        if self.regrow == 0 {
+            self.grow(config, patches, rng);//новая еда
+        }
+    }
+    //шаг жизни еды: рост после задержки и порча
+    pub(crate) fn process(&mut self,
+                          config: &Config,
+                          patches: &FoodPatches,
+                          rng: &mut dyn RngCore) {
+        if self.regrow > 0 {
+            self.regrow -= 1;
+            if self.regrow == 0 {
+                self.grow(config, patches, rng);
+            }
+            return;
+        }
+        self.age += 1;
+        if config.food_spoil_time > 0 && self.age >= config.food_spoil_time {
+            self.consume(config, patches, rng);
+        }
+    }
+    //еда появляется вне препятствий, а если места не нашлось - остается отсутствующей
+    //и ищет место снова на следующем шаге
+    fn grow(&mut self, config: &Config, patches: &FoodPatches, rng: &mut dyn RngCore) {
+        match patches.spawn(config, rng) {
+            Some(position) => self.position = position,
+            None => self.regrow = 1,
+        }
+    }
+}
+
+//задержка роста с экспоненциальным распределением (новая еда появляется потоком Пуассона)
+fn regrow_delay(mean: usize, rng: &mut dyn RngCore) -> usize {
+    if mean == 0 {
+        return 0;
+    }
+    let u: f32 = rng.gen();
+    ((-(mean as f32) * (1.0 - u).ln()).round() as usize).max(1)
+}
+
+/// Место, вокруг которого растет еда
+#[derive(Clone, Debug)]
+pub struct FoodPatch {
+    pub(crate) center: na::Point2<f32>,
+    ///направление дрейфа
+    pub(crate) heading: f32,
+    ///сдвиг сезонного цикла
+    pub(crate) phase: f32,
+}
+
+impl FoodPatch {
+    pub fn center(&self) -> na::Point2<f32> {
+        self.center
+    }
+}
+
+/// Места роста еды (config.food_patches) и время мира для сезонов.
+/// Без мест еда растет равномерно по всему миру
+#[derive(Clone, Debug, Default)]
+pub struct FoodPatches {
+    pub(crate) patches: Vec<FoodPatch>,
+    ///кол. шагов с создания мира
+    pub(crate) time: usize,
+}
+
+impl FoodPatches {
+    pub fn patches(&self) -> &[FoodPatch] {
+        &self.patches
+    }
+}
+
+impl FoodPatches {
+    pub(crate) fn random(config: &Config, rng: &mut dyn RngCore) -> Self {
+        let count = config.food_patches;
+        let patches = (0..count)
+            .map(|k| FoodPatch {
+                center: rng.gen(),
+                heading: rng.gen_range(-PI..PI),
+                //сезоны мест равномерно сдвинуты: богатое место сменяется следующим
+                phase: 2.0 * PI * k as f32 / count as f32,
+            })
+            .collect();
+        Self { patches, time: 0 }
+    }
+    //богатство места сейчас: 1.0 без сезонов, иначе от 0 до 1 по синусоиде
+    pub(crate) fn richness(&self, config: &Config, patch: &FoodPatch) -> f32 {
+        if config.food_season_length == 0 {
+            return 1.0;
         }
+        let season = 2.0 * PI * self.time as f32 / config.food_season_length as f32;
+        0.5 * (1.0 + (season + patch.phase).sin())
     }
-}
\ No newline at end of file
+    //позиция новой еды вне препятствий (None - места не нашлось)
+    pub(crate) fn spawn(&self, config: &Config, rng: &mut dyn RngCore) -> Option<na::Point2<f32>> {
+        free_position(config, config.food_size, || self.sample(config, rng))
+    }
+    //позиция новой еды: равномерно в круге food_patch_radius вокруг места,
+    //выбранного рулеткой по богатству
+    fn sample(&self, config: &Config, rng: &mut dyn RngCore) -> na::Point2<f32> {
+        if self.patches.is_empty() {
+            return rng.gen();
+        }
+        let weights: Vec<f32> = self.patches.iter().map(|p| self.richness(config, p)).collect();
+        let total: f32 = weights.iter().sum();
+        let mut rest = rng.gen_range(0.0..total.max(f32::EPSILON));
+        let mut chosen = self.patches.len() - 1;
+        for (k, weight) in weights.iter().enumerate() {
+            if rest < *weight {
+                chosen = k;
+                break;
+            }
+            rest -= weight;
+        }
+        let radius = config.food_patch_radius * rng.gen::<f32>().sqrt();
+        let angle = rng.gen_range(-PI..PI);
+        let offset = na::Vector2::new(radius * angle.cos(), radius * angle.sin());
+        config.world_topology.confine(self.patches[chosen].center + offset)
+    }
+    //шаг времени: места дрейфуют со скоростью food_patch_drift, от краев квадрата отражаются
+    pub(crate) fn process(&mut self, config: &Config) {
+        self.time += 1;
+        if config.food_patch_drift == 0.0 {
+            return;
+        }
+        for patch in &mut self.patches {
+            let direction = na::Vector2::new(patch.heading.cos(), patch.heading.sin());
+            let moved = patch.center + direction * config.food_patch_drift;
+            if config.world_topology == Topology::Torus {
+                patch.center = Topology::Torus.confine(moved);
+                continue;
+            }
+            //места не покидают единичный квадрат и на плоскости
+            let inside = (0.0..=1.0).contains(&moved.x) && (0.0..=1.0).contains(&moved.y);
+            if !inside {
+                patch.heading = na::wrap(patch.heading + PI, -PI, PI);
+            }
+            patch.center = Topology::Walls.confine(moved);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_chacha::ChaCha8Rng;
+
+    fn config() -> Config {
+        Config {
+            food_patches: 2,
+            food_patch_radius: 0.05,
+            world_topology: Topology::Plane,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn spawns_around_patches() {
+        let config = config();
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let patches = FoodPatches::random(&config, &mut rng);
+        for _ in 0..100 {
+            let position = patches.spawn(&config, &mut rng).unwrap();
+            let nearest = patches
+                .patches()
+                .iter()
+                .map(|p| (p.center() - position).norm())
+                .fold(f32::INFINITY, f32::min);
+            assert!(nearest <= config.food_patch_radius + 1e-6);
+        }
+    }
+
+    #[test]
+    fn waits_for_free_space() {
+        let covered = Config {
+            world_obstacles: vec![Obstacle::Box { min: [0.0, 0.0], max: [1.0, 1.0] }],
+            ..Default::default()
+        };
+        let patches = FoodPatches::default();
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        //места нет: еда не появляется внутри препятствия, а ждет
+        assert_eq!(patches.spawn(&covered, &mut rng), None);
+        let mut food = Food::random(&covered, &patches, &mut rng);
+        assert!(!food.is_present());
+        food.process(&covered, &patches, &mut rng);
+        assert!(!food.is_present());
+        //место освободилось: еда вырастает на следующем шаге
+        food.process(&Config::default(), &patches, &mut rng);
+        assert!(food.is_present());
+    }
+
+    #[test]
+    fn seasons_move_food_between_patches() {
+        let config = Config { food_season_length: 100, ..config() };
+        let mut patches = FoodPatches::random(&config, &mut ChaCha8Rng::seed_from_u64(0));
+        //четверть сезона: первое место в расцвете, второе пустеет
+        patches.time = 25;
+        let (first, second) = (&patches.patches[0], &patches.patches[1]);
+        approx::assert_relative_eq!(patches.richness(&config, first), 1.0);
+        approx::assert_relative_eq!(patches.richness(&config, second), 0.0, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn patches_drift() {
+        let config = Config { food_patch_drift: 0.01, world_topology: Topology::Torus, ..config() };
+        let mut patches = FoodPatches::random(&config, &mut ChaCha8Rng::seed_from_u64(0));
+        let before = patches.patches[0].center();
+        patches.process(&config);
+        let moved = config.world_topology.distance(before, patches.patches[0].center());
+        approx::assert_relative_eq!(moved, 0.01, epsilon = 1e-5);
+        assert_eq!(patches.time, 1);
+    }
+
+    #[test]
+    fn spoils_and_regrows() {
+        let config = Config { food_spoil_time: 10, food_regrow_delay: 5, ..Default::default() };
+        let patches = FoodPatches::default();
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut food = Food::new(na::Point2::new(0.5, 0.5));
+        for _ in 0..5 {
+            food.process(&config, &patches, &mut rng);
+        }
+        //за половину срока питательность упала вдвое
+        approx::assert_relative_eq!(food.nutrient(&config), 0.5 * config.food_nutrient);
+        for _ in 0..5 {
+            food.process(&config, &patches, &mut rng);
+        }
+        //испортилась и пока не выросла снова
+        assert!(!food.is_present());
+        while !food.is_present() {
+            food.process(&config, &patches, &mut rng);
+        }
+        assert_eq!(food.age, 0);
+    }
+
+    #[test]
+    fn regrow_delay_has_configured_mean() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        assert_eq!(regrow_delay(0, &mut rng), 0);
+        let mean = (0..10_000).map(|_| regrow_delay(20, &mut rng)).sum::<usize>() as f32 / 10_000.0;
+        approx::assert_relative_eq!(mean, 20.0, epsilon = 1.0);
+    }
+}
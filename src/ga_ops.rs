@@ -0,0 +1,345 @@
+use crate::*;
+use lib_genetic_algorithm::{CrossoverMethod, MutationMethod, SelectionMethod};
+
+/// Отбор родителей для ГА по конфигурации (config.ga_selection)
+#[derive(Clone, Debug)]
+pub struct GaSelection {
+    kind: Selection,
+    tournament_size: usize,
+    truncation: f32,
+}
+
+impl GaSelection {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            kind: config.ga_selection,
+            tournament_size: config.ga_tournament_size,
+            truncation: config.ga_truncation,
+        }
+    }
+}
+
+impl ga::SelectionMethod for GaSelection {
+    fn select<'a, I>(&self, rng: &mut dyn RngCore, population: &'a [I]) -> &'a I
+    where
+        I: ga::Individual,
+    {
+        match self.kind {
+            Selection::Roulette => ga::RouletteWheelSelection.select(rng, population),
+            //лучшая из tournament_size случайных особей
+            Selection::Tournament => (0..self.tournament_size.max(1))
+                .map(|_| &population[rng.gen_range(0..population.len())])
+                .reduce(|best, i| if i.fitness() > best.fitness() { i } else { best })
+                .unwrap(),
+            //рулетка по рангу: худшая особь - 1 доля, лучшая - population.len() долей
+            Selection::Rank => {
+                let ranked = by_fitness(population);
+                let total = ranked.len() * (ranked.len() + 1) / 2;
+                let mut rest = rng.gen_range(0..total);
+                for (rank, &j) in ranked.iter().enumerate() {
+                    if rest <= rank {
+                        return &population[j];
+                    }
+                    rest -= rank + 1;
+                }
+                &population[ranked[ranked.len() - 1]]
+            }
+            //случайная особь из лучшей доли truncation популяции
+            Selection::Truncation => {
+                let ranked = by_fitness(population);
+                let len = ranked.len();
+                let best = ((len as f32 * self.truncation).ceil() as usize).clamp(1, len);
+                &population[ranked[len - 1 - rng.gen_range(0..best)]]
+            }
+        }
+    }
+}
+
+/// Отбор, запоминающий номера выбранных особей: по ним известны родители потомков,
+/// созданных ГА (у каждого потомка - два выбора подряд)
+pub(crate) struct Recorded<'a, S> {
+    selection: S,
+    picks: &'a RefCell<Vec<usize>>,
+}
+
+impl<'a, S> Recorded<'a, S> {
+    pub(crate) fn new(selection: S, picks: &'a RefCell<Vec<usize>>) -> Self {
+        Self { selection, picks }
+    }
+}
+
+impl<S: ga::SelectionMethod> ga::SelectionMethod for Recorded<'_, S> {
+    fn select<'a, I>(&self, rng: &mut dyn RngCore, population: &'a [I]) -> &'a I
+    where
+        I: ga::Individual,
+    {
+        let chosen = self.selection.select(rng, population);
+        let j = population.iter().position(|i| std::ptr::eq(i, chosen)).unwrap();
+        self.picks.borrow_mut().push(j);
+        chosen
+    }
+}
+
+/// Пара родителей одного потомка: номера особей population, выбранных отбором
+pub(crate) fn select_pair<I: ga::Individual>(selection: &GaSelection,
+                                             rng: &mut dyn RngCore,
+                                             population: &[I]) -> [usize; 2] {
+    let mut pick = || {
+        let chosen = selection.select(rng, population);
+        population.iter().position(|i| std::ptr::eq(i, chosen)).unwrap()
+    };
+    [pick(), pick()]
+}
+
+//номера особей по возрастанию приспособленности
+fn by_fitness<I: ga::Individual>(population: &[I]) -> Vec<usize> {
+    let mut ranked: Vec<usize> = (0..population.len()).collect();
+    ranked.sort_by(|&a, &b| population[a].fitness().total_cmp(&population[b].fitness()));
+    ranked
+}
+
+/// Скрещивание для ГА по конфигурации (config.ga_crossover)
+#[derive(Clone, Debug)]
+pub struct GaCrossover {
+    kind: Crossover,
+}
+
+impl GaCrossover {
+    pub fn new(config: &Config) -> Self {
+        Self { kind: config.ga_crossover }
+    }
+}
+
+impl ga::CrossoverMethod for GaCrossover {
+    fn crossover(
+        &self,
+        rng: &mut dyn RngCore,
+        parent_a: &ga::Chromosome,
+        parent_b: &ga::Chromosome,
+    ) -> ga::Chromosome {
+        match self.kind {
+            Crossover::Uniform => ga::UniformCrossover.crossover(rng, parent_a, parent_b),
+            //начало от первого родителя, конец - от второго. Сети разной топологии
+            //не скрещиваются: потомок - копия первого родителя
+            Crossover::SinglePoint if parent_a.len() == parent_b.len() => {
+                let point = rng.gen_range(0..=parent_a.len());
+                parent_a
+                    .iter()
+                    .take(point)
+                    .chain(parent_b.iter().skip(point))
+                    .copied()
+                    .collect()
+            }
+            Crossover::SinglePoint | Crossover::None => parent_a.clone(),
+        }
+    }
+}
+
+/// Мутация для ГА по типу мозга: у FlexBrain в хромосоме связи с топологией
+/// (Flex1Mutation), у SoftBrain - только веса (mutate_plain)
+#[derive(Clone, Debug)]
+pub struct GaMutation {
+    kind: BrainKind,
+    chance: f32,
+    coeff: f32,
+    inputs: usize,//кол. входов сети (для мутации кол. входов)
+}
+
+impl GaMutation {
+    /// Мутация сети номер nv_num: 0 - намерения (чувства и память), 1 - двигательной
+    pub fn new(config: &Config, nv_num: usize) -> Self {
+        let inputs = match nv_num {
+            0 => config.brain_inputs() + config.brain_memory,
+            _ => config.motive_inputs(),
+        };
+        Self {
+            kind: config.brain_kind,
+            chance: config.ga_mut_chance,
+            coeff: config.ga_mut_coeff,
+            inputs,
+        }
+    }
+}
+
+impl ga::MutationMethod for GaMutation {
+    fn mutate(&self, rng: &mut dyn RngCore, child: &mut ga::Chromosome) {
+        match self.kind {
+            BrainKind::Soft => *child = mutate_plain(self.chance, self.coeff, rng, child),
+            _ => ga::Flex1Mutation::new(self.chance, self.coeff, self.inputs).mutate(rng, child),
+        }
+    }
+}
+
+/// Потомок двух хромосом сети номер nv_num: скрещивание config.ga_crossover и мутация GaMutation
+pub(crate) fn breed(config: &Config,
+                    rng: &mut dyn RngCore,
+                    nv_num: usize,
+                    parent_a: &ga::Chromosome,
+                    parent_b: &ga::Chromosome) -> ga::Chromosome {
+    let mut child = GaCrossover::new(config).crossover(rng, parent_a, parent_b);
+    GaMutation::new(config, nv_num).mutate(rng, &mut child);
+    child
+}
+
+/// Мутация хромосомы из одних весов: каждый ген с вероятностью chance
+/// сдвигается на случайную величину до ±coeff
+pub(crate) fn mutate_plain(chance: f32,
+                           coeff: f32,
+                           rng: &mut dyn RngCore,
+                           chromosome: &ga::Chromosome) -> ga::Chromosome {
+    chromosome
+        .iter()
+        .map(|&gene| {
+            if rng.gen_bool(chance as f64) {
+                let sign = if rng.gen_bool(0.5) { -1.0 } else { 1.0 };
+                gene + sign * coeff * rng.gen::<f32>()
+            } else {
+                gene
+            }
+        })
+        .collect()
+}
+
+/// Хромосомы потомка от одной пары родителей, начиная с хромосомы first (предыдущие
+/// потомок уже получил от ГА): сети (config.brain_networks()) - breed,
+/// следующая за ними хромосома пластичного слоя - breed_plain
+pub(crate) fn breed_all(config: &Config,
+                        rng: &mut dyn RngCore,
+                        first: usize,
+                        parent_a: &[ga::Chromosome],
+                        parent_b: &[ga::Chromosome]) -> Vec<ga::Chromosome> {
+    let networks = config.brain_networks();
+    parent_a
+        .iter()
+        .zip(parent_b)
+        .enumerate()
+        .skip(first)
+        .map(|(n, (a, b))| if n < networks {
+            breed(config, rng, n, a, b)
+        } else {
+            breed_plain(config, rng, a, b)
+        })
+        .collect()
+}
+
+/// Потомок двух хромосом пластичного слоя (без топологии): скрещивание config.ga_crossover
+/// и та же мутация весов, что у SoftBrain. Слои разной ширины (мутация изменила последний
+/// скрытый слой сети) не скрещиваются: потомок - копия первого родителя
+pub(crate) fn breed_plain(config: &Config,
+                          rng: &mut dyn RngCore,
+                          parent_a: &ga::Chromosome,
+                          parent_b: &ga::Chromosome) -> ga::Chromosome {
+    let child = if parent_a.len() == parent_b.len() {
+        GaCrossover::new(config).crossover(rng, parent_a, parent_b)
+    } else {
+        parent_a.clone()
+    };
+    mutate_plain(config.ga_mut_chance, config.ga_mut_coeff, rng, &child)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_chacha::ChaCha8Rng;
+
+    #[derive(Clone, Debug)]
+    struct TestIndividual {
+        fitness: f32,
+        chromosome: ga::Chromosome,
+    }
+
+    impl ga::Individual for TestIndividual {
+        fn create(chromosome: ga::Chromosome) -> Self {
+            Self { fitness: 0.0, chromosome }
+        }
+        fn chromosome(&self) -> &ga::Chromosome {
+            &self.chromosome
+        }
+        fn chromosome_mut(&mut self) -> &mut ga::Chromosome {
+            &mut self.chromosome
+        }
+        fn fitness(&self) -> f32 {
+            self.fitness
+        }
+    }
+
+    #[test]
+    fn plain_mutation_moves_zero_genes_both_ways() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let zeros: ga::Chromosome = vec![0.0; 100].into_iter().collect();
+        let child = mutate_plain(1.0, 0.5, &mut rng, &zeros);
+        assert!(child.iter().any(|&g| g > 0.0));
+        assert!(child.iter().any(|&g| g < 0.0));
+        assert!(child.iter().all(|g| g.abs() <= 0.5));
+    }
+
+    #[test]
+    fn mutation_knows_inputs_of_each_network() {
+        let config = Config { brain_motive: true, brain_memory: 2, ..Default::default() };
+        assert_eq!(GaMutation::new(&config, 0).inputs, config.brain_inputs() + 2);
+        assert_eq!(GaMutation::new(&config, 1).inputs, config.motive_inputs());
+    }
+
+    fn population() -> Vec<TestIndividual> {
+        [2.0, 1.0, 4.0, 3.0]
+            .into_iter()
+            .map(|fitness| TestIndividual { fitness, chromosome: [fitness].into_iter().collect() })
+            .collect()
+    }
+
+    //сколько раз выбрана каждая особь (по приспособленности 1..=4) за 1000 отборов
+    fn histogram(config: Config) -> [usize; 4] {
+        let selection = GaSelection::new(&config);
+        let population = population();
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut counts = [0; 4];
+        for _ in 0..1000 {
+            counts[selection.select(&mut rng, &population).fitness as usize - 1] += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn tournament_prefers_fitter() {
+        let counts = histogram(Config {
+            ga_selection: Selection::Tournament,
+            ga_tournament_size: 3,
+            ..Default::default()
+        });
+        assert!(counts[0] < counts[1] && counts[1] < counts[2] && counts[2] < counts[3]);
+    }
+
+    #[test]
+    fn rank_is_proportional_to_rank() {
+        let counts = histogram(Config { ga_selection: Selection::Rank, ..Default::default() });
+        assert!(counts[0] < counts[1] && counts[1] < counts[2] && counts[2] < counts[3]);
+        assert!(counts[0] > 0);
+    }
+
+    #[test]
+    fn truncation_keeps_only_best() {
+        let counts = histogram(Config {
+            ga_selection: Selection::Truncation,
+            ga_truncation: 0.5,
+            ..Default::default()
+        });
+        assert_eq!(&counts[..2], &[0, 0]);
+        assert!(counts[2] > 0 && counts[3] > 0);
+    }
+
+    #[test]
+    fn single_point_crossover() {
+        let crossover = GaCrossover { kind: Crossover::SinglePoint };
+        let a: ga::Chromosome = [1.0; 8].into_iter().collect();
+        let b: ga::Chromosome = [2.0; 8].into_iter().collect();
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let child: Vec<f32> = crossover.crossover(&mut rng, &a, &b).iter().copied().collect();
+        //сначала гены первого родителя, затем второго
+        let point = child.iter().take_while(|&&g| g == 1.0).count();
+        assert!(child[point..].iter().all(|&g| g == 2.0));
+        //разной длины - копия первого родителя
+        let c: ga::Chromosome = [3.0; 5].into_iter().collect();
+        let child: Vec<f32> = crossover.crossover(&mut rng, &a, &c).iter().copied().collect();
+        assert_eq!(child, vec![1.0; 8]);
+    }
+}
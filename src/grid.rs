@@ -0,0 +1,156 @@
+use crate::*;
+
+/// Равномерная сетка по единичному квадрату мира для быстрого поиска соседей.
+/// Хранит номера объектов по ячейкам, точная проверка расстояния остается за вызывающим.
+#[derive(Clone, Debug)]
+pub(crate) struct SpatialGrid {
+    /// кол. ячеек по стороне квадрата
+    side: usize,
+    /// номера объектов в каждой ячейке (по строкам)
+    cells: Vec<Vec<usize>>,
+}
+
+impl SpatialGrid {
+    /// Сетка с ячейками не меньше cell_size
+    pub(crate) fn new(cell_size: f32) -> Self {
+        assert!(cell_size > 0.0);
+        let side = ((1.0 / cell_size).floor() as usize).clamp(1, 1024);
+
+        Self {
+            side,
+            cells: vec![Vec::new(); side * side],
+        }
+    }
+    /// Перестроение сетки по текущим позициям объектов
+    pub(crate) fn rebuild(&mut self, positions: impl Iterator<Item = na::Point2<f32>>) {
+        for cell in &mut self.cells {
+            cell.clear();
+        }
+        for (i, position) in positions.enumerate() {
+            let cell = self.cell_of(position);
+            self.cells[cell].push(i);
+        }
+    }
+    /// Перенос объекта из старой позиции в новую
+    pub(crate) fn relocate(&mut self, i: usize, from: na::Point2<f32>, to: na::Point2<f32>) {
+        let (from, to) = (self.cell_of(from), self.cell_of(to));
+        if from != to {
+            self.cells[from].retain(|&j| j != i);
+            self.cells[to].push(i);
+        }
+    }
+    /// Номера объектов (по возрастанию), которые могут быть не дальше radius от center
+    pub(crate) fn query(&self,
+                        center: na::Point2<f32>,
+                        radius: f32,
+                        topology: Topology) -> Vec<usize> {
+        let cols = self.span(center.x - radius, center.x + radius, topology);
+        let rows = self.span(center.y - radius, center.y + radius, topology);
+
+        let mut found = Vec::new();
+        for &row in &rows {
+            for &col in &cols {
+                found.extend_from_slice(&self.cells[row * self.side + col]);
+            }
+        }
+        //тот же порядок обхода, что и при полном переборе
+        found.sort_unstable();
+        found
+    }
+}
+
+impl SpatialGrid {
+    fn coord(&self, v: f32) -> isize {
+        (v * self.side as f32).floor() as isize
+    }
+    //объекты за пределами квадрата (плоскость) попадают в крайние ячейки
+    fn cell_of(&self, position: na::Point2<f32>) -> usize {
+        let last = self.side as isize - 1;
+        let col = self.coord(position.x).clamp(0, last) as usize;
+        let row = self.coord(position.y).clamp(0, last) as usize;
+        row * self.side + col
+    }
+    //номера ячеек вдоль одной оси, покрывающие отрезок lo..=hi
+    fn span(&self, lo: f32, hi: f32, topology: Topology) -> Vec<usize> {
+        let (lo, hi) = (self.coord(lo), self.coord(hi));
+        let side = self.side as isize;
+
+        if topology == Topology::Torus {
+            if hi - lo + 1 >= side {
+                (0..self.side).collect()
+            } else {
+                (lo..=hi).map(|c| c.rem_euclid(side) as usize).collect()
+            }
+        } else {
+            let (lo, hi) = (lo.clamp(0, side - 1), hi.clamp(0, side - 1));
+            (lo..=hi).map(|c| c as usize).collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn query_finds_every_close_point() {
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let points: Vec<na::Point2<f32>> = (0..500).map(|_| rng.gen()).collect();
+
+        for topology in [Topology::Torus, Topology::Walls, Topology::Plane] {
+            let mut grid = SpatialGrid::new(0.1);
+            grid.rebuild(points.iter().copied());
+
+            for _ in 0..50 {
+                let center: na::Point2<f32> = rng.gen();
+                let found = grid.query(center, 0.1, topology);
+                let expected: Vec<usize> = (0..points.len())
+                    .filter(|&i| topology.distance(center, points[i]) <= 0.1)
+                    .collect();
+
+                assert!(expected.iter().all(|i| found.contains(i)), "{:?}", topology);
+            }
+        }
+    }
+
+    #[test]
+    fn relocate() {
+        let mut grid = SpatialGrid::new(0.5);
+        grid.rebuild([na::Point2::new(0.1, 0.1)].into_iter());
+        grid.relocate(0, na::Point2::new(0.1, 0.1), na::Point2::new(0.9, 0.9));
+
+        assert!(grid.query(na::Point2::new(0.1, 0.1), 0.1, Topology::Walls).is_empty());
+        assert_eq!(grid.query(na::Point2::new(0.9, 0.9), 0.1, Topology::Walls), vec![0]);
+    }
+
+    #[test]
+    fn same_statistics_as_brute_force() {
+        let brute = Config {
+            sim_spatial_index: false,
+            ..Default::default()
+        };
+        let mut sim_grid = Simulation::from_seed(Default::default(), 3).unwrap();
+        let mut sim_brute = Simulation::from_seed(brute, 3).unwrap();
+
+        let stats_grid = sim_grid.train().unwrap();
+        let stats_brute = sim_brute.train().unwrap();
+        assert_eq!(
+            stats_grid.fitness.avg.to_bits(),
+            stats_brute.fitness.avg.to_bits()
+        );
+        assert_eq!(format!("{}", stats_grid), format!("{}", stats_brute));
+    }
+
+    #[test]
+    fn hunting_same_as_brute_force() {
+        let grid = Config::preset("predators").unwrap();
+        let brute = Config { sim_spatial_index: false, ..grid.clone() };
+        let mut sim_grid = Simulation::from_seed(grid, 5).unwrap();
+        let mut sim_brute = Simulation::from_seed(brute, 5).unwrap();
+
+        let stats_grid = sim_grid.train().unwrap();
+        let stats_brute = sim_brute.train().unwrap();
+        assert_eq!(format!("{}", stats_grid), format!("{}", stats_brute));
+    }
+}
@@ -6,9 +6,19 @@ mod animal_individual;
 mod config;
 mod world;
 mod eye;
-// mod brain;
+mod brain;
 mod flex_brain;
+mod soft_brain;
+mod scripted_brain;
+mod ga_ops;
 mod statistics;
+mod snapshot;
+mod topology;
+mod grid;
+mod lineage;
+mod species;
+mod obstacle;
+mod plastic;
 
 
 pub use self::food::*;
@@ -17,12 +27,22 @@ pub use self::animal_individual::*;
 pub use self::config::*;
 pub use self::world::*;
 pub use self::eye::*;
-// pub use self::brain::*;
+pub use self::brain::*;
 pub use self::flex_brain::*;
+pub use self::soft_brain::*;
+pub use self::scripted_brain::*;
+pub use self::ga_ops::*;
 pub use self::statistics::*;
+pub use self::snapshot::*;
+pub use self::topology::*;
+use self::grid::*;
+pub use self::lineage::*;
+pub use self::species::*;
+pub use self::obstacle::*;
+use self::plastic::*;
 
 
-use rand::{Rng, RngCore};
+use rand::{Rng, RngCore, SeedableRng};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::f32::consts::*;
@@ -30,109 +50,499 @@ use std::fmt;
 use std::cell::RefCell;
 use lib_genetic_algorithm::{Individual, IndividualFlex};
 use rand::rngs::OsRng;
+use rand_chacha::ChaCha8Rng;
 use candle_nn::{Activation};
 use {lib_genetic_algorithm as ga, lib_neural_network as nn, nalgebra as na};
 
 pub struct Simulation {
-    /// генератор случайных значений
-    rng: OsRng,
+    /// генератор случайных значений (один на всю симуляцию, для воспроизводимости)
+    rng: ChaCha8Rng,
     /// текущая конфигурация
     config: Config,
+    /// действующая конфигурация каждого вида птичек
+    species: Vec<SpeciesConfig>,
     /// Мир симуляции, состоит из птичек и еды
     world: World,
     /// номер эпохи обучения, растет неограниченно
     generation: usize,
-    /// номер шага симуляции, растет до config.sim_generation_length
+    /// номер шага в эпохе, растет до config.sim_epoch_length
     age: usize,
+    /// кол. птичек каждого вида, погибших от голода или пойманных хищниками за эпоху
+    deaths: Vec<usize>,
+    /// родословные всех птичек
+    lineage: Lineage,
 }
 
 impl Simulation {
-    pub fn random(config: Config) -> Self {
-        // Создаем новый генератор
-        let mut rng = OsRng::default();
-        let world = World::random(&config, &mut rng);
+    pub fn random(config: Config) -> Result<Self, ConfigError> {
+        // Создаем новый генератор со случайным зерном
+        let rng = ChaCha8Rng::from_rng(OsRng).expect("OsRng недоступен");
 
-        Self {
+        Self::with_rng(config, rng)
+    }
+    /// Воспроизводимая симуляция: одинаковые seed и Config дают одинаковую Statistics
+    pub fn from_seed(config: Config, seed: u64) -> Result<Self, ConfigError> {
+        Self::with_rng(config, ChaCha8Rng::seed_from_u64(seed))
+    }
+
+    fn with_rng(config: Config, mut rng: ChaCha8Rng) -> Result<Self, ConfigError> {
+        config.validate()?;
+        let species = config.species_configs();
+        let world = World::random(&config, &species, &mut rng);
+        let lineage = Lineage::founders(&world);
+
+        let mut sim = Self {
             rng,
             config,
+            deaths: vec![0; species.len()],
+            species,
             world,
             generation: 0,
             age: 0,
-        }
+            lineage,
+        };
+        sim.stagger_ages();
+        Ok(sim)
     }
 
     pub fn config(&self) -> &Config {
         &self.config
     }
 
-    pub fn set_config(&mut self, conf: Config) {
+    pub fn set_config(&mut self, conf: Config) -> Result<(), ConfigError> {
+        conf.validate()?;//неверная конфигурация не меняет симуляцию
         self.config = conf;//заменим конфигурацию на новую
+        self.species = self.config.species_configs();
+        self.deaths = vec![0; self.species.len()];
         //заменим птиц и еду на новую
-        self.world = World::random(&self.config, &mut self.rng);
+        self.world = World::random(&self.config, &self.species, &mut self.rng);
+        self.lineage = Lineage::founders(&self.world);
+        self.stagger_ages();
+        Ok(())
+    }
+    /// Действующая конфигурация каждого вида (Animal::species - номер в этом списке)
+    pub fn species(&self) -> &[SpeciesConfig] {
+        &self.species
     }
 
     pub fn world(&self) -> &World {
         &self.world
     }
 
-    pub fn step(&mut self) -> Option<Statistics> {
+    pub fn lineage(&self) -> &Lineage {
+        &self.lineage
+    }
+
+    /// Шаг симуляции, в конце эпохи - статистика. Ошибка - хромосомы новой птички
+    /// не подошли к мозгу её вида
+    pub fn step(&mut self) -> Result<Option<Statistics>, BrainError> {
+        self.process_foods();//рост, порча еды и дрейф мест её роста
         self.process_collisions();//столкновения с едой
+        self.process_hunting()?;//хищники ловят добычу
         self.process_brains();//общение между птичками и обдумывание перемещения
         self.process_movements();//само перемещение
+        self.process_energy()?;//расход энергии и гибель от голода
         self.try_evolving()//обучение новой популяции
     }
 }
 
 impl Simulation {
-    //обработка столкновения с едой
+    //рост и порча еды, дрейф и сезоны мест её роста
+    fn process_foods(&mut self) {
+        let world = &mut self.world;
+        world.food_patches.process(&self.config);
+        for food in &mut world.foods {
+            food.process(&self.config, &world.food_patches, &mut self.rng);
+        }
+    }
+    //обработка столкновения с едой (хищники еду не едят)
     fn process_collisions(&mut self) {
-        for animal in &mut self.world.animals {
-            for food in &mut self.world.foods {
-                let distance = na::distance(&animal.position, &food.position);
+        let topology = self.config.world_topology;
+        let species = &self.species;
+        let world = &mut self.world;
+        let eaters = world
+            .animals
+            .iter_mut()
+            .filter(|a| a.alive && species[a.species].diet == Diet::Food);
+        if !self.config.sim_spatial_index {
+            for animal in eaters {
+                for food in world.foods.iter_mut() {
+                    if !food.is_present() {
+                        continue;
+                    }
+                    let distance = topology.distance(animal.position, food.position);
+
+                    if distance <= self.config.food_size {
+                        animal.eat(&species[animal.species].config, food.nutrient(&self.config));
+                        food.consume(&self.config, &world.food_patches, &mut self.rng);
+                    }
+                }
+            }
+            return;
+        }
+        //то же самое, но еда ищется только в ближайших ячейках сетки
+        world.food_grid.rebuild(world.foods.iter().map(|f| f.position));
+        for animal in eaters {
+            for i in world.food_grid.query(animal.position, self.config.food_size, topology) {
+                let food = &mut world.foods[i];
+                if !food.is_present() {
+                    continue;
+                }
+                let distance = topology.distance(animal.position, food.position);
 
                 if distance <= self.config.food_size {
-                    animal.satiation += 1;//насыщенность за эпоху
-                    food.position = self.rng.gen();//новая еда
+                    animal.eat(&species[animal.species].config, food.nutrient(&self.config));
+                    let old = food.position;
+                    food.consume(&self.config, &world.food_patches, &mut self.rng);
+                    world.food_grid.relocate(i, old, food.position);
                 }
             }
         }
     }
+    //хищник (Diet::Prey) ловит одну живую птичку, питающуюся едой, в пределах food_size:
+    //она погибает, а хищник насыщается как от свежей еды по конфигурации своего вида
+    fn process_hunting(&mut self) -> Result<(), BrainError> {
+        if self.species.iter().all(|s| s.diet != Diet::Prey) {
+            return Ok(());
+        }
+        let topology = self.config.world_topology;
+        let range = self.config.food_size;
+        if self.config.sim_spatial_index {
+            let world = &mut self.world;
+            world.animal_grid.rebuild(world.animals.iter().map(|a| a.position));
+        }
+        let mut caught = Vec::new();
+        for j in 0..self.world.animals.len() {
+            let hunter = &self.world.animals[j];
+            if !hunter.alive || self.species[hunter.species].diet != Diet::Prey {
+                continue;
+            }
+            let is_prey = |k: &usize| {
+                let a = &self.world.animals[*k];
+                a.alive
+                    && self.species[a.species].diet == Diet::Food
+                    && topology.distance(hunter.position, a.position) <= range
+            };
+            //первая по номеру добыча рядом: по сетке - только из ближайших ячеек
+            let prey = if self.config.sim_spatial_index {
+                self.world
+                    .animal_grid
+                    .query(hunter.position, range, topology)
+                    .into_iter()
+                    .filter(is_prey)
+                    .min()
+            } else {
+                (0..self.world.animals.len()).find(is_prey)
+            };
+            if let Some(k) = prey {
+                let prey = &mut self.world.animals[k];
+                prey.alive = false;
+                prey.energy = 0.0;
+                caught.push(k);
+                let hunter = &mut self.world.animals[j];
+                let config = &self.species[hunter.species].config;
+                hunter.eat(config, config.food_nutrient);
+            }
+        }
+        self.bury(caught, Vec::new())
+    }
     //обдумывание перемещения
     fn process_brains(&mut self) {
-        //птички сканируют пространство
-        for (j, animal) in self.world.animals.iter().enumerate() {
-            let (vf, va) =
-                animal.process_vision(&self.world.foods, &self.world.animals, j);
+        let topology = self.config.world_topology;
+        let world = &mut self.world;
+        if self.config.sim_spatial_index {
+            world.animal_grid.rebuild(world.animals.iter().map(|a| a.position));
+        }
+        let world = &self.world;
+        //птички сканируют пространство (погибших не видно, и они не видят)
+        for animal in world.animals.iter().filter(|a| a.alive) {
+            //еда и птички, которых можно увидеть или услышать
+            let (foods, animals): (Vec<&Food>, Vec<&Animal>) = if self.config.sim_spatial_index {
+                //только из ближайших ячеек сетки
+                let range = animal.eye.fov_range;
+                let foods = world
+                    .food_grid
+                    .query(animal.position, range, topology)
+                    .into_iter()
+                    .map(|i| &world.foods[i])
+                    .filter(|f| f.is_present())
+                    .collect();
+                let range = if self.config.comm_enabled {
+                    range.max(self.config.comm_range)
+                } else {
+                    range
+                };
+                let animals = world
+                    .animal_grid
+                    .query(animal.position, range, topology)
+                    .into_iter()
+                    .map(|i| &world.animals[i])
+                    .filter(|a| a.alive)
+                    .collect();
+                (foods, animals)
+            } else {
+                let animals = world.animals.iter().filter(|a| a.alive).collect();
+                (world.foods.iter().filter(|f| f.is_present()).collect(), animals)
+            };
+            let config = &self.species[animal.species].config;
+            let (vf, va, vn) =
+                animal.process_vision(foods.iter().copied(), animals.iter().copied(), config);
             *(animal.vision_f.borrow_mut()) = vf;
             *(animal.vision_a.borrow_mut()) = va;
+            *(animal.vision_n.borrow_mut()) = vn;
+            if config.eye_obstacles && config.eye_mode == EyeMode::Sectors {
+                *(animal.vision_o.borrow_mut()) = animal.eye.process_vision_obstacles(
+                    animal.position,
+                    animal.rotation,
+                    &world.obstacles,
+                );
+            }
+            if config.eye_mode == EyeMode::Rays {
+                *(animal.vision_r.borrow_mut()) = animal.eye.process_vision_rays(
+                    animal.position,
+                    animal.rotation,
+                    foods.iter().copied(),
+                    animals.iter().copied(),
+                    animal.id,
+                    &world.obstacles,
+                );
+            }
+            //птички слушают сигналы, поданные на прошлом шаге
+            if self.config.comm_enabled {
+                *(animal.hearing.borrow_mut()) =
+                    animal.process_hearing(animals.iter().copied(), &self.config);
+            }
         }
         //птички обдумывают
-        for animal in &mut self.world.animals {
-            animal.process_brain(&self.config);
+        for animal in self.world.animals.iter_mut().filter(|a| a.alive) {
+            animal.process_brain(&self.species[animal.species].config);
         }
     }
     //само перемещение
     fn process_movements(&mut self) {
+        let world = &mut self.world;
+        for animal in world.animals.iter_mut().filter(|a| a.alive) {
+            animal.process_movement(self.config.world_topology, &world.obstacles);
+        }
+    }
+    //расход энергии, птички с нулевой энергией погибают
+    fn process_energy(&mut self) -> Result<(), BrainError> {
+        let continuous = self.config.sim_cadence == Cadence::Continuous;
+        let mut dead = Vec::new();
+        let mut expired = Vec::new();
+        for (j, animal) in self.world.animals.iter_mut().enumerate() {
+            if !animal.alive {
+                continue;
+            }
+            animal.lived += 1;
+            animal.steps += 1;
+            animal.hunger += 1;
+            animal.ate = false;
+            if self.config.energy_enabled {
+                animal.energy -= animal.energy_cost(&self.species[animal.species].config);
+                if animal.energy <= 0.0 {
+                    animal.energy = 0.0;
+                    animal.alive = false;
+                    dead.push(j);
+                    continue;
+                }
+            }
+            //в Cadence::Continuous время жизни отсчитывается в шагах
+            let lifespan = animal.generation_lifetime * self.config.sim_epoch_length;
+            if continuous && animal.steps >= lifespan {
+                animal.alive = false;
+                expired.push(j);
+            }
+        }
+        self.bury(dead, expired)
+    }
+    //погибшие (dead) и дожившие свой век (expired) птички: в Cadence::Continuous на их месте
+    //сразу рождаются потомки, иначе с EnergyDeath::Respawn погибшие заменяются новыми
+    fn bury(&mut self, dead: Vec<usize>, expired: Vec<usize>) -> Result<(), BrainError> {
+        for &j in &dead {
+            self.deaths[self.world.animals[j].species] += 1;
+        }
+        for &j in dead.iter().chain(&expired) {
+            self.lineage.death(self.world.animals[j].id, self.generation);
+        }
+
+        if self.config.sim_cadence == Cadence::Continuous {
+            for j in dead.into_iter().chain(expired) {
+                self.offspring(j)?;
+            }
+        } else if self.config.energy_death == EnergyDeath::Respawn {
+            for j in dead {
+                self.respawn(j)?;
+            }
+        }
+        Ok(())
+    }
+    //в Cadence::Continuous птички-основатели начинают с разным возрастом в шагах,
+    //чтобы не погибать все одновременно
+    fn stagger_ages(&mut self) {
+        if self.config.sim_cadence != Cadence::Continuous {
+            return;
+        }
         for animal in &mut self.world.animals {
-            animal.process_movement();
+            animal.steps = self.rng.gen_range(0..self.config.sim_epoch_length);
         }
     }
-    //обучение новых птичек при накоплении достаточного возраста
-    fn try_evolving(&mut self) -> Option<Statistics> {
+    //новая птичка вида species
+    fn born(&mut self,
+            species: usize,
+            chromosomes: Vec<ga::Chromosome>,
+            id: AnimalId) -> Result<Animal, BrainError> {
+        let config = &self.species[species].config;
+        let mut animal = Animal::from_chromosome(config, &mut self.rng, chromosomes, id)?;
+        animal.species = species;
+        Ok(animal)
+    }
+    //вместо погибшей птички появляется новая с мозгом живой птички того же вида
+    fn respawn(&mut self, j: usize) -> Result<(), BrainError> {
+        let species = self.world.animals[j].species;
+        //живых нет - гены остаются свои
+        let parent = self.pick_by_energy(species).unwrap_or(j);
+        let parent = &self.world.animals[parent];
+        let (chromosomes, parent) = (parent.as_chromosomes(), parent.id);
+
+        let id = self.world.new_id();
+        self.world.animals[j] = self.born(species, chromosomes, id)?;
+        self.lineage.birth(id, vec![parent], self.generation);
+        Ok(())
+    }
+    //живая птичка вида species, выбранная рулеткой по энергии
+    fn pick_by_energy(&mut self, species: usize) -> Option<usize> {
+        let total: f32 = self
+            .world
+            .animals
+            .iter()
+            .filter(|a| a.alive && a.species == species)
+            .map(|a| a.energy)
+            .sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut rest = self.rng.gen_range(0.0..total);
+        let mut last = None;
+        for (j, animal) in self.world.animals.iter().enumerate() {
+            if !animal.alive || animal.species != species || animal.energy <= 0.0 {
+                continue;
+            }
+            if rest < animal.energy {
+                return Some(j);
+            }
+            rest -= animal.energy;
+            last = Some(j);
+        }
+        last//на случай ошибок округления
+    }
+    //обучение новых птичек при накоплении достаточного возраста (config.sim_cadence)
+    fn try_evolving(&mut self) -> Result<Option<Statistics>, BrainError> {
         self.age += 1;
-        if self.age > self.config.sim_generation_length / 4 {
-            //каждый 625 шаг меняется поколение и те птички, у которых lifetime уменьшился
-            //до 0 заменяются
-            Some(self.evolve())
+        let epoch_over = self.age >= self.config.sim_epoch_length;
+        let evolving = match self.config.sim_cadence {
+            Cadence::Interval => epoch_over,
+            Cadence::SteadyState => {
+                //за эпоху заменяется примерно вся популяция каждого вида, по одной птичке
+                for k in 0..self.species.len() {
+                    let population = self.species[k].config.world_animals.max(1);
+                    let interval = (self.config.sim_epoch_length / population).max(1);
+                    if self.age % interval == 0 {
+                        self.replace_worst(k, interval)?;
+                    }
+                }
+                epoch_over
+            }
+            Cadence::Extinction => epoch_over || self.world.animals.iter().all(|a| !a.alive),
+            Cadence::Continuous => epoch_over,
+        };
+        if evolving {
+            self.evolve().map(Some)
+        } else {
+            Ok(None)
         }
-        else {
-            None
+    }
+    //замена худшей птички вида species (погибшей или с наименьшей приспособленностью за эпоху
+    //среди проживших не меньше min_lived шагов) потомком двух родителей, выбранных отбором ГА
+    fn replace_worst(&mut self, species: usize, min_lived: usize) -> Result<(), BrainError> {
+        let networks = self.config.brain_networks();
+        if networks == 0 {
+            return Ok(());//без генов потомок не отличается от родителей
         }
+        let scores: Vec<f32> = self
+            .world
+            .animals
+            .iter()
+            .map(|a| a.epoch_fitness(&self.config, self.age))
+            .collect();
+        let worst = self
+            .world
+            .animals
+            .iter()
+            .enumerate()
+            .filter(|(_, a)| a.species == species && (!a.alive || a.lived >= min_lived))
+            .min_by(|(j, a), (k, b)| a.alive.cmp(&b.alive).then(scores[*j].total_cmp(&scores[*k])))
+            .map(|(j, _)| j);
+        let Some(worst) = worst else {
+            return Ok(());
+        };
+        self.lineage.death(self.world.animals[worst].id, self.generation);
+        self.offspring(worst)
+    }
+    //на месте птички j рождается потомок двух живых родителей того же вида, выбранных отбором ГА
+    //по приспособленности за эпоху; появляется рядом с первым родителем
+    fn offspring(&mut self, j: usize) -> Result<(), BrainError> {
+        let species = self.world.animals[j].species;
+        let scores: Vec<f32> = self
+            .world
+            .animals
+            .iter()
+            .map(|a| a.epoch_fitness(&self.config, self.age))
+            .collect();
+        let alive: Vec<usize> = (0..self.world.animals.len())
+            .filter(|&k| {
+                let animal = &self.world.animals[k];
+                animal.alive && animal.species == species && k != j
+            })
+            .collect();
+        //живых нет - гены остаются свои
+        let parents = if alive.is_empty() {
+            [j, j]
+        } else {
+            let selection = GaSelection::new(&self.config);
+            let individuals: Vec<AnimalIndividual> = alive
+                .iter()
+                .map(|&k| AnimalIndividual::scored(&self.world.animals[k], 0, scores[k]))
+                .collect();
+            select_pair(&selection, &mut self.rng, &individuals).map(|i| alive[i])
+        };
+        let (a, b) = (&self.world.animals[parents[0]], &self.world.animals[parents[1]]);
+        let (chrs_a, chrs_b) = (a.as_chromosomes(), b.as_chromosomes());
+        let position = a.position;
+        let mut ids = vec![a.id, b.id];
+        ids.dedup();
+        //все сети потомка от одних и тех же родителей, за сетями - пластичный слой
+        let config = &self.species[species].config;
+        let chrs = breed_all(config, &mut self.rng, 0, &chrs_a, &chrs_b);
+
+        let id = self.world.new_id();
+        let mut child = self.born(species, chrs, id)?;
+        let near = self.species[species].config.eye_fov_range / 2.0;
+        let offset = na::Vector2::new(self.rng.gen_range(-near..=near),
+                                      self.rng.gen_range(-near..=near));
+        let place = self.config.world_topology.confine(position + offset);
+        //в препятствии потомок не появляется: тогда - на месте родителя
+        child.position = if is_free(&self.config, place, 0.0) { place } else { position };
+        self.lineage.birth(id, ids, self.generation);
+        self.world.animals[j] = child;
+        Ok(())
     }
     //само обучение одной эпохи эволюции/замена некоторых птичек на новых
-    fn evolve(&mut self) -> Statistics {
+    fn evolve(&mut self) -> Result<Statistics, BrainError> {
+        let steps = self.age;//длина прошедшей эпохи
         self.age = 0;
         self.generation += 1; //увеличивает номер поколения
+        let deaths = std::mem::replace(&mut self.deaths, vec![0; self.species.len()]);
         //средняя насыщенность каждой птички за весь её возраст (используется при обучении в ГА)
         for animal in &mut self.world.animals {
             animal.generation_age += 1;//возраст птички в эпохах
@@ -141,68 +551,191 @@ impl Simulation {
                 (animal.satiation_avg * (animal.generation_age -1) as f32
                 + animal.satiation as f32)
                 / animal.generation_age as f32;
+            //средняя приспособленность птички за весь её возраст
+            animal.fitness_avg =
+                (animal.fitness_avg * (animal.generation_age -1) as f32
+                + animal.epoch_fitness(&self.config, steps))
+                / animal.generation_age as f32;
+        }
+        if self.species.len() == 1 {
+            return self.evolve_species(0, deaths[0]);
         }
-        //Создаем генетический алгоритм
+        //каждый вид эволюционирует в своей популяции ГА, итог - по всем птичкам
+        let fitness = FitnessSummary::new(self.world.animals.iter().map(|a| a.fitness_avg));
+        let species: Vec<Statistics> = deaths
+            .iter()
+            .enumerate()
+            .map(|(k, &deaths)| self.evolve_species(k, deaths))
+            .collect::<Result<_, _>>()?;
+        Ok(Statistics {
+            generation: self.generation - 1,
+            species_name: String::new(),
+            ga: None,
+            fitness,
+            population: self.world.animals.len(),
+            food_eaten: species.iter().map(|s| s.food_eaten).sum(),
+            deaths: deaths.iter().sum(),
+            species,
+        })
+    }
+    //эволюция птичек вида k в его собственной популяции ГА
+    fn evolve_species(&mut self, k: usize, deaths: usize) -> Result<Statistics, BrainError> {
+        let config = self.species[k].config.clone();
+        //номера птичек вида в мире
+        let members: Vec<usize> = (0..self.world.animals.len())
+            .filter(|&j| self.world.animals[j].species == k)
+            .collect();
+        //съедено за поколение (до сброса насыщенности)
+        let food_eaten = members.iter().map(|&j| self.world.animals[j].satiation).sum();
+        let fitness = FitnessSummary::new(members.iter().map(|&j| self.world.animals[j].fitness_avg));
+        //у написанного вручную поведения (BrainKind::Scripted) нет генов для ГА,
+        //а в Cadence::SteadyState и Continuous птички уже заменялись по одной в течение эпохи
+        let replaced = matches!(config.sim_cadence, Cadence::SteadyState | Cadence::Continuous);
+        if config.brain_networks() == 0 || replaced {
+            return self.evolve_without_ga(k, &members, fitness, food_eaten, deaths);
+        }
+        //Создаем генетический алгоритм: отбор, скрещивание и мутация - по конфигурации вида,
+        //отбор запоминает выбранных родителей
+        let picks = RefCell::new(Vec::new());
         let ga = ga::GeneticFlexAlgorithm::new(
-            self.config.sim_generation_length,//для генерации времени жизни
-            ga::RouletteWheelSelection,
-            ga::UniformCrossover,
-            ga::Flex1Mutation::new(self.config.ga_mut_chance,
-                                   self.config.ga_mut_coeff,
-                                   self.config.eye_cells),//для мутации кол. входов
+            config.sim_lifetime_max,//время жизни новой птички в эпохах
+            Recorded::new(GaSelection::new(&config), &picks),
+            GaCrossover::new(&config),
+            GaMutation::new(&config, 0),
         );
-        // // сортируем птичек с минимальным насыщением (в самом верху)
-        // self.world.animals.sort_by(|a, b| a.satiation.cmp(&b.satiation));
         //Получаем (0 - сеть намерения (intention)):
-        // - все птички в виде AnimalIndividual
-        let all_i: Vec<AnimalIndividual> = self
-            .world
-            .animals
+        // - все птички вида в виде AnimalIndividual
+        let all_i: Vec<AnimalIndividual> = members
             .iter()
-            .map(|a| AnimalIndividual::from_animal(a,0))
+            .map(|&j| AnimalIndividual::from_animal(&self.world.animals[j], 0))
             .collect();
-        // //Получаем (1 - двигательная (motive) сеть):
-        // // - все птички в виде AnimalIndividual
-        // let all_m: Vec<AnimalIndividual> = self
-        //     .world
-        //     .animals
-        //     .iter()
-        //     .map(|a| AnimalIndividual::from_animal(a,1))
-        //     .collect();
         //Шаг эволюции
         //На входе: генератор случайных значений и список всех птичек в виде AnimalIndividual
-        //На выходе: новая популяция птичек (AnimalIndividual)
-        //и статистика по прошлой популяции
-        let (individuals_i, stats_i) =
-            ga.evolve(&mut self.rng, &all_i);
-        // let (individuals_m, stats_m) =
-        //     ga.evolve_1(&mut self.rng, &all_m);
+        //На выходе: новая популяция птичек (AnimalIndividual): потомки с временем жизни
+        //и флагом замены
+        let (individuals_i, _) = ga.evolve(&mut self.rng, &all_i);
+        //родители каждого потомка ГА (по порядку потомков) - номера в members
+        let picks = picks.take();
+        let mut pairs = picks.chunks_exact(2);
+        //все хромосомы каждой птички прошлой популяции
+        let genes: Vec<Vec<ga::Chromosome>> = members
+            .iter()
+            .map(|&j| self.world.animals[j].as_chromosomes())
+            .collect();
+        let ids: Vec<AnimalId> = members.iter().map(|&j| self.world.animals[j].id).collect();
+        //номера в members от лучшей к худшей птичке
+        let mut ranked: Vec<usize> = (0..members.len()).collect();
+        ranked.sort_by(|&a, &b| all_i[b].fitness().total_cmp(&all_i[a].fitness()));
+        //кол. нейронов по слоям каждой сети лучшей птички
+        let best = &self.world.animals[members[ranked[0]]];
+        let layers: Vec<Vec<usize>> = (0..config.brain_networks())
+            .map(|n| best.brain.neurons_by_layer(n))
+            .collect();
+        //лучшие птички (config.ga_elitism) не заменяются и сохраняют свое время жизни
+        let elite: Vec<usize> = ranked.into_iter().take(config.ga_elitism).collect();
+        let mut changed = 0;
         //Замена птичек в популяции
-        for (j, animal) in self.world.animals.iter_mut().enumerate() {
-            //время жизни в поколениях уменьшаем для "плохих" птичек
-            //при этом "хорошие" птички сохраняют свою жизнь дольше
-            if individuals_i[j].changed() == true {//под замену
-                let chrs: Vec<ga::Chromosome> = vec![individuals_i[j].chromosome().clone()];//,
-                                                     // individuals_m[j].chromosome().clone()];
-                *animal = Animal::from_chromosome(&self.config, &mut self.rng, chrs);
-            } else {//замены нет
-                animal.satiation = 0;
+        for (i, &j) in members.iter().enumerate() {
+            let individual = &individuals_i[i];
+            let parents = if individual.changed() {
+                let pair = pairs.next().expect("ГА выбрал меньше родителей, чем создал потомков");
+                Some([pair[0], pair[1]])
+            } else {
+                None
             };
-            animal.generation_lifetime = individuals_i[j].life_time();
+            let is_elite = elite.contains(&i);
+            //время жизни в поколениях ведет ГА: "хорошие" птички живут дольше
+            let lifetime = if is_elite {
+                self.world.animals[j].generation_lifetime
+            } else {
+                individual.life_time()
+            };
+            match parents {
+                Some([a, b]) if !is_elite => {//под замену
+                    //сеть намерения - от ГА, остальные сети и пластичный слой - от той же пары
+                    let mut chrs = vec![individual.chromosome().clone()];
+                    chrs.extend(breed_all(&config, &mut self.rng, 1, &genes[a], &genes[b]));
+                    let mut parents = vec![ids[a], ids[b]];
+                    parents.dedup();
+                    let id = self.world.new_id();
+                    self.lineage.death(ids[i], self.generation - 1);
+                    self.lineage.birth(id, parents, self.generation);
+                    self.world.animals[j] = self.born(k, chrs, id)?;
+                    changed += 1;
+                }
+                _ if !self.world.animals[j].alive => {//погибшая птичка возрождается с теми же генами
+                    let id = self.world.new_id();
+                    self.lineage.death(ids[i], self.generation - 1);
+                    self.lineage.birth(id, vec![ids[i]], self.generation);
+                    self.world.animals[j] = self.born(k, genes[i].clone(), id)?;
+                }
+                _ => {//замены нет
+                    let animal = &mut self.world.animals[j];
+                    animal.satiation = 0;
+                    animal.lived = 0;
+                }
+            }
+            //время жизни всегда в пределах sim_lifetime_min..=sim_lifetime_max (элита с
+            //истекшим временем живет еще sim_lifetime_min эпох)
+            self.world.animals[j].generation_lifetime =
+                lifetime.clamp(config.sim_lifetime_min, config.sim_lifetime_max);
         }
-        //Статистика по прошлой популяции
-        Statistics {
+        //Статистика по прошлой популяции: общая для всех сетей и топология каждой сети
+        let ga_stats = GaStatistics {
+            fitness,
+            changed_count: changed,
+            networks: layers,
+        };
+        Ok(Statistics {
             generation: self.generation - 1,
-            ga: vec![stats_i], //stats_m],
+            species_name: self.species[k].name.clone(),
+            ga: Some(ga_stats),
+            fitness,
+            population: members.len(),
+            food_eaten,
+            deaths,
+            species: Vec::new(),
+        })
+    }
+    //смена эпохи без ГА: погибшие птички вида k возрождаются, остальные продолжают жить
+    fn evolve_without_ga(&mut self,
+                         k: usize,
+                         members: &[usize],
+                         fitness: FitnessSummary,
+                         food_eaten: usize,
+                         deaths: usize) -> Result<Statistics, BrainError> {
+        for &j in members {
+            if !self.world.animals[j].alive {
+                let chrs = self.world.animals[j].as_chromosomes();
+                let parent = self.world.animals[j].id;
+                let id = self.world.new_id();
+                self.lineage.death(parent, self.generation - 1);
+                self.lineage.birth(id, vec![parent], self.generation);
+                self.world.animals[j] = self.born(k, chrs, id)?;
+            } else {
+                let animal = &mut self.world.animals[j];
+                animal.satiation = 0;
+                animal.lived = 0;
+            }
         }
+        Ok(Statistics {
+            generation: self.generation - 1,
+            species_name: self.species[k].name.clone(),
+            ga: None,
+            fitness,
+            population: members.len(),
+            food_eaten,
+            deaths,
+            species: Vec::new(),
+        })
     }
 }
 
 impl Simulation {
-    pub fn train(&mut self) -> Statistics { //, rng: &mut dyn RngCore
+    pub fn train(&mut self) -> Result<Statistics, BrainError> { //, rng: &mut dyn RngCore
         loop {
-            if let Some(statistics) = self.step() { //rng
-                return statistics;
+            if let Some(statistics) = self.step()? { //rng
+                return Ok(statistics);
             }
         }
     }
@@ -211,20 +744,445 @@ impl Simulation {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
+    use test_case::test_case;
     // use rand::SeedableRng;
     // use rand_chacha::ChaCha8Rng;
 
+    fn same_statistics(a: &Statistics, b: &Statistics) -> bool {
+        a.generation == b.generation
+            && a.ga.is_some() == b.ga.is_some()
+            && a.ga.iter().zip(&b.ga).all(|(a, b)| {
+                a.min_fitness().to_bits() == b.min_fitness().to_bits()
+                    && a.max_fitness().to_bits() == b.max_fitness().to_bits()
+                    && a.avg_fitness().to_bits() == b.avg_fitness().to_bits()
+                    && a.median_fitness().to_bits() == b.median_fitness().to_bits()
+                    && a.changed_count() == b.changed_count()
+            })
+    }
+
+    #[test]
+    fn same_seed_same_statistics() {
+        let mut sim_a = Simulation::from_seed(Default::default(), 42).unwrap();
+        let mut sim_b = Simulation::from_seed(Default::default(), 42).unwrap();
+
+        for _ in 0..3 {
+            let stats_a = sim_a.train().unwrap();
+            let stats_b = sim_b.train().unwrap();
+            assert!(same_statistics(&stats_a, &stats_b), "{} != {}", stats_a, stats_b);
+        }
+    }
+
+    #[test]
+    fn motive_network_evolves_with_intention() {
+        //без скрещивания и мутации потомок - копия первого родителя
+        let config = Config {
+            brain_motive: true,
+            ga_crossover: Crossover::None,
+            ga_mut_chance: 0.0,
+            ..Config::preset("tiny-debug").unwrap()
+        };
+        let mut sim = Simulation::from_seed(config, 3).unwrap();
+        let genes = |a: &Animal| -> Vec<Vec<f32>> {
+            a.as_chromosomes().iter().map(|c| c.iter().copied().collect()).collect()
+        };
+        let before: HashMap<AnimalId, Vec<Vec<f32>>> =
+            sim.world().animals().iter().map(|a| (a.id(), genes(a))).collect();
+        let stats = sim.train().unwrap();
+        //топология каждой сети, у каждой птички обе сети
+        assert_eq!(stats.ga.unwrap().networks().len(), 2);
+        assert!(sim.world().animals().iter().all(|a| a.as_chromosomes().len() == 2));
+        //обе сети потомка - от одного и того же родителя
+        let mut born = 0;
+        for animal in sim.world().animals().iter().filter(|a| !before.contains_key(&a.id())) {
+            let parents = &sim.lineage().get(animal.id()).unwrap().parents;
+            assert_eq!(genes(animal), before[&parents[0]]);
+            born += 1;
+        }
+        assert!(born > 0);
+    }
+
+    #[test]
+    fn soft_brain_evolves_for_generations() {
+        let config = Config {
+            brain_kind: BrainKind::Soft,
+            brain_motive: true,
+            ..Config::preset("tiny-debug").unwrap()
+        };
+        let sizes = |sim: &Simulation| -> Vec<Vec<usize>> {
+            sim.world()
+                .animals()
+                .iter()
+                .map(|a| a.as_chromosomes().iter().map(|c| c.len()).collect())
+                .collect()
+        };
+        let mut sim = Simulation::from_seed(config, 8).unwrap();
+        let born = sizes(&sim);
+        let mut changed = 0;
+        for _ in 0..5 {
+            let stats = sim.train().unwrap();
+            let ga = stats.ga.unwrap();
+            assert_eq!(ga.networks().len(), 2);
+            //3 сегмента по 2 канала, скрытый слой из 2 нейронов, скорость, поворот и 9 намерений
+            assert_eq!(ga.neurons_by_layer(0), &[6, 2, 2 + 9]);
+            assert_eq!(ga.neurons_by_layer(1), &[2 + 9, 11, 2]);
+            changed += ga.changed_count();
+        }
+        //мутация весов не меняет размер хромосом
+        assert!(changed > 0);
+        assert_eq!(sizes(&sim), born);
+    }
+
     #[test]
-    #[ignore]
-    fn test() {
-        // let mut rng = ChaCha8Rng::from_seed(Default::default());
-        let mut sim = Simulation::random(Default::default());//, &mut rng
+    fn scripted_brain_runs_without_ga() {
+        let config = Config {
+            brain_kind: BrainKind::Scripted,
+            ..Config::preset("tiny-debug").unwrap()
+        };
+        let mut sim = Simulation::from_seed(config, 5).unwrap();
+        let stats = sim.train().unwrap();
+        assert!(stats.ga.is_none());
+        assert_eq!(stats.to_record().changed_count, 0);
+        assert!(stats.fitness.max >= stats.fitness.min);
+    }
 
-        let avg_fitness = (0..10)
-            .map(|_| sim.train().ga[0].avg_fitness())//&mut rng
-            .sum::<f32>()
-            / 10.0;
+    #[test]
+    fn elite_animals_are_kept() {
+        let config = Config {
+            ga_selection: Selection::Tournament,
+            ga_crossover: Crossover::SinglePoint,
+            ga_elitism: 4,
+            ..Config::preset("tiny-debug").unwrap()
+        };
+        let mut sim = Simulation::from_seed(config, 7).unwrap();
+        let genes = |a: &Animal| -> Vec<Vec<f32>> {
+            a.as_chromosomes().iter().map(|c| c.iter().copied().collect()).collect()
+        };
+        for _ in 0..3 {
+            let before: HashMap<AnimalId, Vec<Vec<f32>>> =
+                sim.world().animals().iter().map(|a| (a.id(), genes(a))).collect();
+            let stats = sim.train().unwrap();
+            //из 6 птичек 4 лучших - элита: ГА заменяет только 2 остальные
+            let changed = stats.ga.as_ref().unwrap().changed_count();
+            assert!(changed <= 2);
+            let kept: Vec<&Animal> = sim
+                .world()
+                .animals()
+                .iter()
+                .filter(|a| before.get(&a.id()) == Some(&genes(a)))
+                .collect();
+            assert_eq!(kept.len() + changed, 6);
+            //среди сохраненных - лучшая птичка
+            let best = kept.iter().map(|a| a.fitness_avg).fold(f32::MIN, f32::max);
+            assert_eq!(best, stats.fitness.max);
+        }
+    }
 
-        approx::assert_relative_eq!(31.944998, avg_fitness);
+    #[test]
+    fn lifetime_stays_in_range() {
+        let config = Config {
+            sim_lifetime_min: 2,
+            sim_lifetime_max: 3,
+            ga_elitism: 4,
+            energy_enabled: true,
+            ..Config::preset("tiny-debug").unwrap()
+        };
+        let mut sim = Simulation::from_seed(config, 9).unwrap();
+        for _ in 0..6 {
+            sim.train().unwrap();
+            for animal in sim.world().animals() {
+                assert!((2..=3).contains(&animal.generation_lifetime), "{}", animal.generation_lifetime);
+            }
+        }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn steady_state_replaces_one_at_a_time() {
+        let config = Config {
+            sim_cadence: Cadence::SteadyState,
+            ..Config::preset("tiny-debug").unwrap()
+        };
+        let mut sim = Simulation::from_seed(config, 11).unwrap();
+        //125 шагов эпохи на 6 птичек - замена каждые 20 шагов
+        for _ in 0..19 {
+            assert!(sim.step().unwrap().is_none());
+        }
+        let ids: Vec<AnimalId> = sim.world().animals().iter().map(|a| a.id()).collect();
+        sim.step().unwrap();
+        let replaced = sim
+            .world()
+            .animals()
+            .iter()
+            .zip(&ids)
+            .filter(|(a, id)| a.id() != **id)
+            .count();
+        assert_eq!(replaced, 1);
+        //статистика по-прежнему раз в эпоху, без ГА
+        let stats = sim.train().unwrap();
+        assert_eq!(stats.generation, 0);
+        assert!(stats.ga.is_none());
+    }
+
+    #[test_case(Inheritance::Lamarckian, true; "lamarckian")]
+    #[test_case(Inheritance::Darwinian, false; "darwinian")]
+    fn child_starts_from_parent_weights(brain_inheritance: Inheritance, learned: bool) {
+        //без скрещивания и мутации пластичный слой потомка - копия слоя первого родителя
+        let config = Config {
+            sim_cadence: Cadence::Continuous,
+            brain_plasticity: Plasticity::Hebbian,
+            brain_inheritance,
+            brain_motive: true,
+            ga_crossover: Crossover::None,
+            ga_mut_chance: 0.0,
+            ..Config::preset("tiny-debug").unwrap()
+        };
+        let mut sim = Simulation::from_seed(config, 17).unwrap();
+        //веса каждой птички при рождении
+        let mut initial: HashMap<AnimalId, Vec<f32>> =
+            sim.world().animals().iter().map(|a| (a.id(), a.brain.learned())).collect();
+        let mut born = 0;
+        for _ in 0..500 {
+            sim.step().unwrap();
+            let animals = sim.world().animals();
+            for child in animals.iter().filter(|a| !initial.contains_key(&a.id())) {
+                let parents = &sim.lineage().get(child.id()).unwrap().parents;
+                //родитель мог быть заменен на этом же шаге
+                let Some(parent) = animals.iter().find(|a| a.id() == parents[0]) else {
+                    continue;
+                };
+                //родитель успел научиться: выученные веса отличаются от начальных
+                if parent.brain.learned() == initial[&parent.id()] {
+                    continue;
+                }
+                //по Ламарку потомок начинает с выученных весов родителя, по Дарвину - с начальных
+                assert_eq!(child.brain.learned() == parent.brain.learned(), learned);
+                assert_eq!(child.brain.learned() == initial[&parent.id()], !learned);
+                born += 1;
+            }
+            for animal in animals {
+                initial.entry(animal.id()).or_insert_with(|| animal.brain.learned());
+            }
+        }
+        assert!(born > 0);
+    }
+
+    #[test]
+    fn extinction_cadence_evolves_when_all_dead() {
+        let config = Config {
+            sim_cadence: Cadence::Extinction,
+            sim_epoch_length: 10_000,
+            ..starving(EnergyDeath::Remove)
+        };
+        let mut sim = Simulation::from_seed(config, 1).unwrap();
+        let stats = sim.train().unwrap();
+        //все погибли задолго до конца эпохи
+        assert_eq!(stats.deaths, stats.population);
+        assert!(sim.world().animals().iter().all(|a| a.is_alive()));
+    }
+
+    #[test]
+    fn continuous_offspring_is_born_near_parent() {
+        let config = Config {
+            sim_cadence: Cadence::Continuous,
+            ..Config::preset("tiny-debug").unwrap()
+        };
+        let near = config.eye_fov_range / 2.0 * SQRT_2;
+        let topology = config.world_topology;
+        let mut sim = Simulation::from_seed(config, 13).unwrap();
+        let mut births = 0;
+        for _ in 0..300 {
+            let ids: Vec<AnimalId> = sim.world().animals().iter().map(|a| a.id()).collect();
+            sim.step().unwrap();
+            for (animal, id) in sim.world().animals().iter().zip(&ids) {
+                if animal.id() == *id {
+                    continue;
+                }
+                births += 1;
+                //первый родитель жив и рядом
+                let parents = &sim.lineage().get(animal.id()).unwrap().parents;
+                let parent = sim.world().animals().iter().find(|a| a.id() == parents[0]).unwrap();
+                assert!(topology.distance(animal.position(), parent.position()) <= near + 1e-6);
+            }
+            assert!(sim.world().animals().iter().all(|a| a.is_alive()));
+        }
+        //время жизни основателей - 1 эпоха (125 шагов) со сдвигом, все успели смениться
+        assert!(births >= 6);
+    }
+
+    #[test]
+    fn eaten_food_regrows_later() {
+        let config = Config {
+            food_regrow_delay: 1_000_000,
+            food_size: 0.05,
+            ..Config::preset("tiny-debug").unwrap()
+        };
+        let mut sim = Simulation::from_seed(config, 3).unwrap();
+        let stats = sim.train().unwrap();
+        //каждую еду можно съесть только один раз: новая за эпоху не вырастает
+        let absent = sim.world().foods().iter().filter(|f| !f.is_present()).count();
+        assert_eq!(stats.food_eaten, absent);
+        assert!(stats.food_eaten <= sim.world().foods().len());
+    }
+
+    #[test]
+    fn predators_catch_prey_and_species_evolve_apart() {
+        let config = Config {
+            energy_death: EnergyDeath::Remove,
+            species: vec![
+                Species { name: "prey".to_string(), animals: 8, ..Default::default() },
+                Species {
+                    name: "hawk".to_string(),
+                    animals: 3,
+                    diet: Diet::Prey,
+                    eye_cells: Some(5),
+                    ..Default::default()
+                },
+            ],
+            ..Config::preset("tiny-debug").unwrap()
+        };
+        let mut sim = Simulation::from_seed(config, 17).unwrap();
+        //первый хищник оказывается на месте первой птички-добычи
+        sim.world.animals[8].position = sim.world.animals[0].position;
+        sim.step().unwrap();
+        assert!(!sim.world().animals()[0].is_alive());
+        assert_eq!(sim.world().animals()[8].satiation, 1);
+
+        let stats = sim.train().unwrap();
+        assert_eq!(stats.species.len(), 2);
+        assert_eq!((stats.species[0].species_name.as_str(), stats.species[0].population), ("prey", 8));
+        assert_eq!((stats.species[1].species_name.as_str(), stats.species[1].population), ("hawk", 3));
+        assert!(stats.species[0].deaths >= 1);
+        assert_eq!(stats.to_records().len(), 2);
+        //каждый вид эволюционирует отдельно: новые птички остаются своего вида со своим зрением
+        for (j, animal) in sim.world().animals().iter().enumerate() {
+            let (species, cells) = if j < 8 { (0, 3) } else { (1, 5) };
+            assert_eq!(animal.species(), species);
+            assert_eq!(animal.vision().len(), cells);
+        }
+    }
+
+    #[test]
+    fn animals_and_food_stay_out_of_obstacles() {
+        let mut sim = Simulation::from_seed(Config::preset("obstacles").unwrap(), 9).unwrap();
+        for _ in 0..300 {
+            sim.step().unwrap();
+            let world = sim.world();
+            for obstacle in world.obstacles() {
+                assert!(world.animals().iter().all(|a| obstacle.distance(a.position()) > 0.0));
+                assert!(world
+                    .foods()
+                    .iter()
+                    .filter(|f| f.is_present())
+                    .all(|f| obstacle.distance(f.position()) > 0.0));
+            }
+        }
+    }
+
+    #[test]
+    fn internal_senses_are_appended() {
+        let config = Config {
+            eye_food: false,
+            eye_animals: false,
+            sense_speed: true,
+            sense_heading: true,
+            sense_hunger: true,
+            sense_satiation: true,
+            sense_lifetime: true,
+            energy_enabled: true,
+            ..Config::preset("tiny-debug").unwrap()
+        };
+        //без зрения: скорость, синус и косинус поворота, голод, насыщенность, остаток жизни
+        assert_eq!(config.brain_inputs(), 6);
+        let mut sim = Simulation::from_seed(config, 5).unwrap();
+        for _ in 0..20 {
+            sim.step().unwrap();
+        }
+        let config = sim.config();
+        for animal in sim.world().animals().iter().filter(|a| a.is_alive()) {
+            let senses = animal.process_senses(config);
+            assert_eq!(senses.len(), 6);
+            approx::assert_relative_eq!(senses[1].powi(2) + senses[2].powi(2), 1.0, epsilon = 1e-5);
+            assert!(senses.iter().all(|s| (-1.0..=1.0).contains(s)), "{:?}", senses);
+            assert!(animal.hunger <= animal.steps);
+        }
+    }
+
+    #[test]
+    fn ate_flag_lasts_one_step() {
+        let mut sim = Simulation::from_seed(Config::preset("tiny-debug").unwrap(), 5).unwrap();
+        let animal = &mut sim.world.animals[0];
+        animal.eat(&sim.config, 1.0);
+        assert!(animal.ate);
+        //флаг еды виден мозгу только на шаге, когда еда съедена
+        sim.step().unwrap();
+        assert!(sim.world().animals().iter().all(|a| !a.ate));
+    }
+
+    #[test]
+    fn eye_mode_is_chosen_per_species() {
+        let config = Config {
+            world_topology: Topology::Walls,
+            species: vec![
+                Species { name: "sectors".to_string(), animals: 3, ..Default::default() },
+                Species {
+                    name: "rays".to_string(),
+                    animals: 3,
+                    eye_mode: Some(EyeMode::Rays),
+                    ..Default::default()
+                },
+            ],
+            ..Config::preset("tiny-debug").unwrap()
+        };
+        let mut sim = Simulation::from_seed(config, 21).unwrap();
+        //лучи: по каналу на еду, птичек, препятствия и стены в каждом из 3 лучей
+        assert_eq!(sim.species()[0].config().brain_inputs(), 2 * 3);
+        assert_eq!(sim.species()[1].config().brain_inputs(), 4 * 3);
+        sim.train().unwrap();
+        for _ in 0..10 {
+            sim.step().unwrap();
+        }
+        for animal in sim.world().animals() {
+            assert_eq!(animal.vision_rays().len(), 3);
+            if animal.species() == 0 {
+                assert!(animal.vision_rays().iter().all(|ray| ray.is_none()));
+            } else {
+                //с лучами сегменты не считаются
+                assert!(animal.vision().iter().all(|&f| f == 0.0));
+                assert!(animal.vision_animals().iter().all(|&(a, _)| a.is_none()));
+            }
+        }
+    }
+
+    fn starving(energy_death: EnergyDeath) -> Config {
+        Config {
+            world_foods: 0,
+            energy_enabled: true,
+            energy_decay: 0.01,//погибают примерно за 80 шагов
+            energy_death,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn starving_animals_respawn() {
+        let mut sim = Simulation::from_seed(starving(EnergyDeath::Respawn), 1).unwrap();
+
+        let stats = sim.train().unwrap();
+        assert!(stats.deaths >= stats.population);
+        assert!(sim.world().animals().iter().all(|a| a.is_alive()));
+    }
+
+    #[test]
+    fn starving_animals_removed_until_next_epoch() {
+        let mut sim = Simulation::from_seed(starving(EnergyDeath::Remove), 1).unwrap();
+        for _ in 0..200 {
+            sim.step().unwrap();
+        }
+        assert!(sim.world().animals().iter().all(|a| !a.is_alive()));
+
+        let stats = sim.train().unwrap();
+        assert_eq!(stats.deaths, stats.population);
+        assert!(sim.world().animals().iter().all(|a| a.is_alive()));
+        assert!(sim.world().animals().iter().all(|a| a.energy() == 1.0));
+    }
+}
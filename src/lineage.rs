@@ -0,0 +1,216 @@
+use crate::*;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+
+/// Родословная одной птички
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LineageRecord {
+    pub id: AnimalId,
+    /// родители (пусто у птичек первого поколения)
+    pub parents: Vec<AnimalId>,
+    /// поколение, в котором птичка появилась
+    pub birth_generation: usize,
+    /// последнее поколение, в котором птичка жила (None - жива)
+    pub death_generation: Option<usize>,
+}
+
+/// Родословные всех птичек симуляции
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Lineage {
+    records: BTreeMap<AnimalId, LineageRecord>,
+}
+
+impl Lineage {
+    pub fn get(&self, id: AnimalId) -> Option<&LineageRecord> {
+        self.records.get(&id)
+    }
+
+    pub fn records(&self) -> impl Iterator<Item = &LineageRecord> {
+        self.records.values()
+    }
+    /// Все предки птички (родители, их родители и т.д.) по возрастанию номера
+    pub fn ancestors(&self, id: AnimalId) -> Vec<AnimalId> {
+        let mut found = BTreeSet::new();
+        let mut queue: Vec<AnimalId> = self.parents(id).to_vec();
+        while let Some(parent) = queue.pop() {
+            if found.insert(parent) {
+                queue.extend_from_slice(self.parents(parent));
+            }
+        }
+        found.into_iter().collect()
+    }
+    /// Родоначальники (птички без родителей), у которых есть живые потомки,
+    /// и их живые потомки (включая самого родоначальника, если он жив)
+    pub fn surviving_lineages(&self) -> BTreeMap<AnimalId, Vec<AnimalId>> {
+        let mut lineages: BTreeMap<AnimalId, Vec<AnimalId>> = BTreeMap::new();
+        for record in self.records.values().filter(|r| r.death_generation.is_none()) {
+            let mut founders: Vec<AnimalId> = self
+                .ancestors(record.id)
+                .into_iter()
+                .filter(|&a| self.parents(a).is_empty())
+                .collect();
+            if record.parents.is_empty() {
+                founders.push(record.id);
+            }
+            for founder in founders {
+                lineages.entry(founder).or_default().push(record.id);
+            }
+        }
+        lineages
+    }
+    /// Родословное дерево в формате GraphViz DOT (живые птички закрашены)
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph lineage {\n    node [shape=box];\n");
+        for record in self.records.values() {
+            let (death, style) = match record.death_generation {
+                Some(death) => (death.to_string(), ""),
+                None => (String::new(), ", style=filled"),
+            };
+            let _ = writeln!(
+                dot,
+                "    \"{}\" [label=\"{}\\n{}..{}\"{}];",
+                record.id.0, record.id, record.birth_generation, death, style
+            );
+        }
+        for record in self.records.values() {
+            for parent in &record.parents {
+                let _ = writeln!(dot, "    \"{}\" -> \"{}\";", parent.0, record.id.0);
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+impl Lineage {
+    /// Родословная первого поколения мира
+    pub(crate) fn founders(world: &World) -> Self {
+        let mut lineage = Self::default();
+        for animal in &world.animals {
+            lineage.birth(animal.id, Vec::new(), 0);
+        }
+        lineage
+    }
+
+    pub(crate) fn birth(&mut self, id: AnimalId, parents: Vec<AnimalId>, generation: usize) {
+        self.records.insert(id, LineageRecord {
+            id,
+            parents,
+            birth_generation: generation,
+            death_generation: None,
+        });
+    }
+
+    pub(crate) fn death(&mut self, id: AnimalId, generation: usize) {
+        if let Some(record) = self.records.get_mut(&id) {
+            record.death_generation = Some(generation);
+        }
+    }
+
+    fn parents(&self, id: AnimalId) -> &[AnimalId] {
+        self.records.get(&id).map_or(&[][..], |r| r.parents.as_slice())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(n: u64) -> AnimalId {
+        AnimalId(n)
+    }
+
+    //0 и 1 - родоначальники, 2 от 0 и 1, 3 от 2, 4 от 1 (погибла)
+    fn lineage() -> Lineage {
+        let mut lineage = Lineage::default();
+        lineage.birth(id(0), vec![], 0);
+        lineage.birth(id(1), vec![], 0);
+        lineage.birth(id(2), vec![id(0), id(1)], 1);
+        lineage.birth(id(3), vec![id(2)], 2);
+        lineage.birth(id(4), vec![id(1)], 2);
+        lineage.death(id(0), 0);
+        lineage.death(id(2), 1);
+        lineage.death(id(4), 2);
+        lineage
+    }
+
+    #[test]
+    fn ancestors() {
+        let lineage = lineage();
+
+        assert_eq!(lineage.ancestors(id(3)), vec![id(0), id(1), id(2)]);
+        assert_eq!(lineage.ancestors(id(4)), vec![id(1)]);
+        assert!(lineage.ancestors(id(0)).is_empty());
+    }
+
+    #[test]
+    fn surviving_lineages() {
+        let lineages = lineage().surviving_lineages();
+
+        let expected: BTreeMap<AnimalId, Vec<AnimalId>> =
+            [(id(0), vec![id(3)]), (id(1), vec![id(1), id(3)])].into_iter().collect();
+        assert_eq!(lineages, expected);
+    }
+
+    #[test]
+    fn to_dot() {
+        let dot = lineage().to_dot();
+
+        assert!(dot.starts_with("digraph lineage {"));
+        assert!(dot.contains("\"3\" [label=\"#3\\n2..\", style=filled];"));
+        assert!(dot.contains("\"4\" [label=\"#4\\n2..2\"];"));
+        assert!(dot.contains("\"0\" -> \"2\";"));
+        assert!(dot.contains("\"2\" -> \"3\";"));
+    }
+
+    #[test]
+    fn recorded_parents_are_the_crossed_pair() {
+        //без мутации каждый ген потомка - ген одного из двух записанных родителей
+        let config = Config {
+            ga_crossover: Crossover::SinglePoint,
+            ga_mut_chance: 0.0,
+            ..Config::preset("tiny-debug").unwrap()
+        };
+        let mut sim = Simulation::from_seed(config, 4).unwrap();
+        let before: Vec<(AnimalId, Vec<f32>)> = sim
+            .world()
+            .animals()
+            .iter()
+            .map(|a| (a.id(), a.as_chromosome(0).iter().copied().collect()))
+            .collect();
+        let genes = |id: AnimalId| &before.iter().find(|(a, _)| *a == id).unwrap().1;
+        sim.train().unwrap();
+        let mut born = 0;
+        for animal in sim.world().animals() {
+            let parents = &sim.lineage().get(animal.id()).unwrap().parents;
+            if parents.is_empty() || before.iter().any(|(id, _)| *id == animal.id()) {
+                continue;
+            }
+            let (a, b) = (genes(parents[0]), genes(*parents.last().unwrap()));
+            let child: Vec<f32> = animal.as_chromosome(0).iter().copied().collect();
+            assert_eq!(child.len(), a.len());
+            assert!(child.iter().enumerate().all(|(i, g)| *g == a[i] || *g == b[i]));
+            born += 1;
+        }
+        assert!(born > 0);
+    }
+
+    #[test]
+    fn simulation_records_births_and_deaths() {
+        let mut sim = Simulation::from_seed(Config::preset("tiny-debug").unwrap(), 2).unwrap();
+        for _ in 0..5 {
+            sim.train().unwrap();
+        }
+        let lineage = sim.lineage();
+
+        for animal in sim.world().animals() {
+            let record = lineage.get(animal.id()).expect("нет родословной живой птички");
+            assert_eq!(record.death_generation, None);
+            for parent in &record.parents {
+                assert!(lineage.get(*parent).is_some());
+            }
+        }
+        let alive = lineage.records().filter(|r| r.death_generation.is_none()).count();
+        assert_eq!(alive, sim.world().animals().len());
+    }
+}
@@ -0,0 +1,350 @@
+use crate::*;
+
+/// Неподвижное препятствие в единичном квадрате мира (world_obstacles в Config).
+/// Птички сквозь препятствия не проходят, еда и птички внутри них не появляются
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Obstacle {
+    /// круг
+    Circle { center: [f32; 2], radius: f32 },
+    /// прямоугольник со сторонами вдоль осей
+    Box { min: [f32; 2], max: [f32; 2] },
+    /// ломаная - тонкая стена из отрезков между точками
+    Polyline { points: Vec<[f32; 2]> },
+}
+
+//отступ от края препятствия, чтобы после скольжения птичка оставалась снаружи
+const EDGE: f32 = 1e-5;
+//сколько раз за шаг ищется свободное от препятствий место для новой еды
+const SPAWN_ATTEMPTS: usize = 100;
+//размер сетки проверки свободного места (has_free_space)
+const FREE_GRID: usize = 64;
+
+impl Obstacle {
+    /// Препятствие имеет размер (радиус, стороны, хотя бы один отрезок)
+    pub fn is_valid(&self) -> bool {
+        match self {
+            Self::Circle { radius, .. } => *radius > 0.0,
+            Self::Box { min, max } => min[0] < max[0] && min[1] < max[1],
+            Self::Polyline { points } => points.len() >= 2,
+        }
+    }
+    /// Расстояние от точки до препятствия (0 внутри)
+    pub fn distance(&self, p: na::Point2<f32>) -> f32 {
+        match self {
+            Self::Circle { center, radius } => ((p - point(center)).norm() - radius).max(0.0),
+            Self::Box { min, max } => (p - clamp(p, min, max)).norm(),
+            Self::Polyline { points } => segments(points)
+                .map(|(a, b)| (p - nearest_on_segment(p, a, b)).norm())
+                .fold(f32::INFINITY, f32::min),
+        }
+    }
+    /// Расстояние вдоль луча из origin в направлении dir (единичный вектор) до препятствия
+    /// (0, если origin внутри), None - луч не попадает
+    pub fn ray(&self, origin: na::Point2<f32>, dir: na::Vector2<f32>) -> Option<f32> {
+        match self {
+            Self::Circle { center, radius } => ray_circle(point(center) - origin, *radius, dir),
+            //пересечение полос вдоль каждой оси
+            Self::Box { min, max } => {
+                let (mut near, mut far) = (0.0f32, f32::INFINITY);
+                for axis in 0..2 {
+                    let (o, d) = (origin[axis], dir[axis]);
+                    if d.abs() < f32::EPSILON {
+                        if o < min[axis] || o > max[axis] {
+                            return None;
+                        }
+                        continue;
+                    }
+                    let (t1, t2) = ((min[axis] - o) / d, (max[axis] - o) / d);
+                    near = near.max(t1.min(t2));
+                    far = far.min(t1.max(t2));
+                }
+                (near <= far).then_some(near)
+            }
+            Self::Polyline { points } => segments(points)
+                .filter_map(|(a, b)| ray_segment(origin, dir, a, b))
+                .min_by(|a, b| a.total_cmp(b)),
+        }
+    }
+    //точка p в копии мира, ближайшей к препятствию (на торе препятствие видно через край)
+    pub(crate) fn local(&self, p: na::Point2<f32>, topology: Topology) -> na::Point2<f32> {
+        let anchor = match self {
+            Self::Circle { center, .. } => point(center),
+            Self::Box { min, max } => na::center(&point(min), &point(max)),
+            Self::Polyline { points } => point(&points[0]),
+        };
+        anchor + topology.delta(anchor, p)
+    }
+    //путь из from в to упирается в препятствие
+    fn blocks(&self, from: na::Point2<f32>, to: na::Point2<f32>) -> bool {
+        match self {
+            Self::Circle { center, radius } => (to - point(center)).norm() < *radius,
+            Self::Box { min, max } => {
+                (min[0] < to.x && to.x < max[0]) && (min[1] < to.y && to.y < max[1])
+            }
+            Self::Polyline { points } => {
+                segments(points).any(|(a, b)| segments_cross(from, to, a, b))
+            }
+        }
+    }
+    //куда сдвигается птичка, шагнувшая из from в to внутрь препятствия: на край препятствия
+    //(круг, прямоугольник) или вдоль стены без составляющей шага поперек неё (ломаная)
+    fn slide(&self, from: na::Point2<f32>, to: na::Point2<f32>) -> na::Point2<f32> {
+        match self {
+            Self::Circle { center, radius } => {
+                let center = point(center);
+                let out = if to == center { from - center } else { to - center };
+                center + out.normalize() * (radius + EDGE)
+            }
+            //к ближайшей стороне
+            Self::Box { min, max } => {
+                let mut to = to;
+                let sides = [
+                    (to.x - min[0], 0, min[0] - EDGE),
+                    (max[0] - to.x, 0, max[0] + EDGE),
+                    (to.y - min[1], 1, min[1] - EDGE),
+                    (max[1] - to.y, 1, max[1] + EDGE),
+                ];
+                let (_, axis, edge) = sides
+                    .into_iter()
+                    .min_by(|a, b| a.0.total_cmp(&b.0))
+                    .unwrap();
+                to[axis] = edge;
+                to
+            }
+            Self::Polyline { points } => {
+                //нормаль ближайшего к птичке отрезка
+                let (a, b) = segments(points)
+                    .min_by(|x, y| {
+                        let dx = (from - nearest_on_segment(from, x.0, x.1)).norm();
+                        let dy = (from - nearest_on_segment(from, y.0, y.1)).norm();
+                        dx.total_cmp(&dy)
+                    })
+                    .unwrap();
+                let along = (b - a).normalize();
+                let step = to - from;
+                from + along * step.dot(&along)
+            }
+        }
+    }
+}
+
+/// Шаг птички из from на step с учетом препятствий: у препятствия птичка скользит
+/// вдоль него, а если и так путь закрыт - остается на месте
+pub(crate) fn obstructed_step(from: na::Point2<f32>,
+                              step: na::Vector2<f32>,
+                              obstacles: &[Obstacle],
+                              topology: Topology) -> na::Vector2<f32> {
+    let mut step = step;
+    for obstacle in obstacles {
+        let local = obstacle.local(from, topology);
+        if obstacle.blocks(local, local + step) {
+            step = obstacle.slide(local, local + step) - local;
+        }
+    }
+    let blocked = obstacles.iter().any(|o| {
+        let local = o.local(from, topology);
+        o.blocks(local, local + step)
+    });
+    if blocked {
+        na::Vector2::zeros()
+    } else {
+        step
+    }
+}
+
+/// Позиция не ближе margin к препятствиям
+pub(crate) fn is_free(config: &Config, position: na::Point2<f32>, margin: f32) -> bool {
+    config
+        .world_obstacles
+        .iter()
+        .all(|o| o.distance(o.local(position, config.world_topology)) > margin)
+}
+
+/// Случайная позиция sample() вне препятствий (не ближе margin). None - места так и не
+/// нашлось за SPAWN_ATTEMPTS попыток
+pub(crate) fn free_position(config: &Config,
+                            margin: f32,
+                            mut sample: impl FnMut() -> na::Point2<f32>) -> Option<na::Point2<f32>> {
+    (0..SPAWN_ATTEMPTS)
+        .map(|_| sample())
+        .find(|&position| is_free(config, position, margin))
+}
+
+/// Есть ли вне препятствий место для птички: проверка узлов сетки FREE_GRID x FREE_GRID
+pub(crate) fn has_free_space(config: &Config) -> bool {
+    let step = 1.0 / FREE_GRID as f32;
+    (0..FREE_GRID * FREE_GRID).any(|k| {
+        let cell = na::Point2::new((k % FREE_GRID) as f32 + 0.5, (k / FREE_GRID) as f32 + 0.5);
+        is_free(config, cell * step, 0.0)
+    })
+}
+
+/// Расстояние вдоль луча в направлении dir до круга радиуса radius с центром center
+/// (относительно начала луча), 0 - начало внутри круга
+pub(crate) fn ray_circle(center: na::Vector2<f32>,
+                         radius: f32,
+                         dir: na::Vector2<f32>) -> Option<f32> {
+    let c = center.norm_squared() - radius * radius;
+    if c <= 0.0 {
+        return Some(0.0);
+    }
+    let b = -center.dot(&dir);
+    let disc = b * b - c;
+    if b > 0.0 || disc < 0.0 {
+        return None;
+    }
+    Some(-b - disc.sqrt())
+}
+
+fn point(p: &[f32; 2]) -> na::Point2<f32> {
+    na::Point2::new(p[0], p[1])
+}
+
+fn clamp(p: na::Point2<f32>, min: &[f32; 2], max: &[f32; 2]) -> na::Point2<f32> {
+    na::Point2::new(p.x.clamp(min[0], max[0]), p.y.clamp(min[1], max[1]))
+}
+
+fn segments(points: &[[f32; 2]]) -> impl Iterator<Item = (na::Point2<f32>, na::Point2<f32>)> + '_ {
+    points.windows(2).map(|w| (point(&w[0]), point(&w[1])))
+}
+
+fn nearest_on_segment(p: na::Point2<f32>,
+                      a: na::Point2<f32>,
+                      b: na::Point2<f32>) -> na::Point2<f32> {
+    let ab = b - a;
+    let len = ab.norm_squared();
+    if len == 0.0 {
+        return a;
+    }
+    a + ab * ((p - a).dot(&ab) / len).clamp(0.0, 1.0)
+}
+
+fn cross(a: na::Vector2<f32>, b: na::Vector2<f32>) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+//расстояние вдоль луча до отрезка ab
+fn ray_segment(origin: na::Point2<f32>,
+               dir: na::Vector2<f32>,
+               a: na::Point2<f32>,
+               b: na::Point2<f32>) -> Option<f32> {
+    let ab = b - a;
+    let denom = cross(dir, ab);
+    if denom.abs() < f32::EPSILON {
+        return None;//параллельны
+    }
+    let ao = a - origin;
+    let t = cross(ao, ab) / denom;
+    let u = cross(ao, dir) / denom;
+    (t >= 0.0 && (0.0..=1.0).contains(&u)).then_some(t)
+}
+
+//отрезки pq и ab пересекаются (или касаются)
+fn segments_cross(p: na::Point2<f32>,
+                  q: na::Point2<f32>,
+                  a: na::Point2<f32>,
+                  b: na::Point2<f32>) -> bool {
+    let (pq, ab) = (q - p, b - a);
+    let denom = cross(pq, ab);
+    if denom.abs() < f32::EPSILON {
+        return false;//параллельны
+    }
+    let ap = a - p;
+    let t = cross(ap, ab) / denom;
+    let u = cross(ap, pq) / denom;
+    (0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn circle() -> Obstacle {
+        Obstacle::Circle { center: [0.5, 0.5], radius: 0.1 }
+    }
+
+    fn wall() -> Obstacle {
+        Obstacle::Polyline { points: vec![[0.2, 0.2], [0.2, 0.8]] }
+    }
+
+    #[test]
+    fn distance() {
+        let p = na::Point2::new(0.8, 0.5);
+        approx::assert_relative_eq!(circle().distance(p), 0.2, epsilon = 1e-6);
+        let cell = Obstacle::Box { min: [0.4, 0.4], max: [0.6, 0.6] };
+        approx::assert_relative_eq!(cell.distance(p), 0.2, epsilon = 1e-6);
+        assert_eq!(cell.distance(na::Point2::new(0.5, 0.5)), 0.0);
+        approx::assert_relative_eq!(wall().distance(na::Point2::new(0.3, 0.9)), 0.1414, epsilon = 1e-4);
+    }
+
+    #[test]
+    fn rays_hit_first_surface() {
+        let origin = na::Point2::new(0.1, 0.5);
+        let right = na::Vector2::x();
+        approx::assert_relative_eq!(circle().ray(origin, right).unwrap(), 0.3, epsilon = 1e-6);
+        approx::assert_relative_eq!(wall().ray(origin, right).unwrap(), 0.1, epsilon = 1e-6);
+        let cell = Obstacle::Box { min: [0.4, 0.4], max: [0.6, 0.6] };
+        approx::assert_relative_eq!(cell.ray(origin, right).unwrap(), 0.3, epsilon = 1e-6);
+        //мимо и назад
+        assert_eq!(circle().ray(origin, na::Vector2::y()), None);
+        assert_eq!(circle().ray(origin, -right), None);
+    }
+
+    #[test]
+    fn animals_slide_along_obstacles() {
+        let topology = Topology::Plane;
+        //прямо в стену: остается на месте
+        let from = na::Point2::new(0.19, 0.5);
+        let step = obstructed_step(from, na::Vector2::new(0.02, 0.0), &[wall()], topology);
+        approx::assert_relative_eq!(step.norm(), 0.0);
+        //наискосок в стену: скользит вдоль неё
+        let step = obstructed_step(from, na::Vector2::new(0.02, 0.02), &[wall()], topology);
+        approx::assert_relative_eq!(step, na::Vector2::new(0.0, 0.02), epsilon = 1e-6);
+        //в круг: остается снаружи
+        let from = na::Point2::new(0.5, 0.39);
+        let step = obstructed_step(from, na::Vector2::new(0.005, 0.02), &[circle()], topology);
+        assert!(circle().distance(from + step) > 0.0);
+        assert!(step.x > 0.0);
+    }
+
+    #[test]
+    fn free_position_avoids_obstacles() {
+        let config = Config {
+            world_obstacles: vec![Obstacle::Box { min: [0.0, 0.0], max: [0.9, 1.0] }],
+            ..Default::default()
+        };
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        for _ in 0..20 {
+            let position = free_position(&config, 0.0, || rng.gen()).unwrap();
+            assert!(position.x > 0.9);
+        }
+        assert!(has_free_space(&config));
+    }
+
+    #[test]
+    fn no_position_inside_covering_obstacle() {
+        let config = Config {
+            world_obstacles: vec![Obstacle::Box { min: [0.0, 0.0], max: [1.0, 1.0] }],
+            ..Default::default()
+        };
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        assert_eq!(free_position(&config, 0.0, || rng.gen()), None);
+        assert!(!has_free_space(&config));
+    }
+
+    #[test]
+    fn obstacles_from_toml() {
+        let config = Config::from_toml_str(
+            r#"
+            [[world_obstacles]]
+            circle = { center = [0.5, 0.5], radius = 0.1 }
+
+            [[world_obstacles]]
+            polyline = { points = [[0.2, 0.2], [0.2, 0.8]] }
+            "#,
+        )
+            .unwrap();
+        assert_eq!(config.world_obstacles, vec![circle(), wall()]);
+    }
+}
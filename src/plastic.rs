@@ -0,0 +1,209 @@
+use crate::*;
+
+/// Предел веса пластичной связи, чтобы правило Хебба не разгоняло веса
+const PLASTIC_LIMIT: f32 = 1.0;
+
+/// Пластичный слой FlexBrain - выходной слой сети намерения: связи нейронов последнего
+/// скрытого слоя (и смещения) со всеми выходами сети. Веса связей меняются в течение жизни
+/// по правилу config.brain_plasticity. Гены слоя - начальные (или выученные,
+/// Inheritance::Lamarckian) веса и скорости обучения связей
+#[derive(Clone, Debug)]
+pub(crate) struct PlasticLayer {
+    rule: Plasticity,
+    inheritance: Inheritance,
+    activation: BrainActivation,//функция активации выходов (config.brain_output_activation)
+    inputs: usize,//кол. входов - нейронов последнего скрытого слоя сети намерения
+    outputs: usize,//кол. выходов сети намерения
+    initial: Vec<f32>,//веса при рождении, outputs строк по (смещение, inputs весов)
+    weights: Vec<f32>,//текущие, выученные веса
+    rates: Vec<f32>,//скорость обучения каждой связи
+    pre: Vec<f32>,//1 (смещение) и входы последнего прохода
+    post: Vec<f32>,//выходы последнего прохода (-0.5..0.5 относительно середины)
+}
+
+impl PlasticLayer {
+    /// Кол. выходов сети намерения: скорость и поворот, намерения, сигнал, память
+    pub(crate) fn outputs(config: &Config) -> usize {
+        let intentions = if config.brain_motive { config.brain_intentions } else { 0 };
+        2 + intentions + config.brain_signals() + config.brain_memory
+    }
+    /// Кол. входов слоя по конфигурации: последний скрытый слой сети намерения,
+    /// без скрытых слоев - входной слой (чувства и память)
+    pub(crate) fn inputs(config: &Config) -> usize {
+        config
+            .brain_hidden
+            .last()
+            .map_or(config.brain_inputs() + config.brain_memory, |l| l.neurons)
+    }
+    /// Кол. генов слоя: веса и скорости обучения
+    pub(crate) fn genes(config: &Config) -> usize {
+        2 * Self::outputs(config) * (Self::inputs(config) + 1)
+    }
+    /// Слой с небольшими случайными весами и скоростями обучения
+    pub(crate) fn random(config: &Config, rng: &mut dyn RngCore) -> Self {
+        let genes: ga::Chromosome = (0..Self::genes(config))
+            .map(|_| rng.gen_range(-0.1..=0.1))
+            .collect();
+        Self::from_chromosome(config, Self::inputs(config), &genes)
+    }
+    /// Слой из хромосомы: первая половина - веса, вторая - скорости обучения.
+    /// inputs - кол. нейронов последнего скрытого слоя сети: если мутация его изменила,
+    /// связи новых нейронов начинаются с нуля, связи удаленных отбрасываются
+    pub(crate) fn from_chromosome(config: &Config, inputs: usize, chromosome: &ga::Chromosome) -> Self {
+        let outputs = Self::outputs(config);
+        assert!(!chromosome.is_empty() && chromosome.len() % (2 * outputs) == 0,
+                "хромосома не совпадает с пластичным слоем");
+        let genes: Vec<f32> = chromosome.iter().copied().collect();
+        let (initial, rates) = genes.split_at(genes.len() / 2);
+        let width = initial.len() / outputs;
+        let resize = |genes: &[f32]| -> Vec<f32> {
+            genes
+                .chunks_exact(width)
+                .flat_map(|row| {
+                    let mut row = row.to_vec();
+                    row.resize(inputs + 1, 0.0);
+                    row
+                })
+                .collect()
+        };
+        let initial = resize(initial);
+        Self {
+            rule: config.brain_plasticity,
+            inheritance: config.brain_inheritance,
+            activation: config.brain_output_activation,
+            inputs,
+            outputs,
+            weights: initial.clone(),
+            initial,
+            rates: resize(rates),
+            pre: vec![0.0; inputs + 1],
+            post: vec![0.0; outputs],
+        }
+    }
+    /// Гены слоя: начальные веса (по Дарвину) или выученные (по Ламарку), скорости обучения
+    pub(crate) fn as_chromosome(&self) -> ga::Chromosome {
+        let weights = match self.inheritance {
+            Inheritance::Darwinian => &self.initial,
+            Inheritance::Lamarckian => &self.weights,
+        };
+        weights.iter().chain(&self.rates).copied().collect()
+    }
+    /// Кол. весов (для расхода энергии на мозг)
+    pub(crate) fn size(&self) -> usize {
+        self.weights.len()
+    }
+    /// Кол. выходов слоя
+    pub(crate) fn width(&self) -> usize {
+        self.outputs
+    }
+    /// Выученные веса
+    pub(crate) fn weights(&self) -> &[f32] {
+        &self.weights
+    }
+    pub(crate) fn set_weights(&mut self, weights: Vec<f32>) {
+        assert_eq!(weights.len(), self.weights.len(), "кол. весов не совпадает с пластичным слоем");
+        self.weights = weights;
+    }
+    /// Прямой проход: выходы сети намерения из выходов последнего скрытого слоя
+    pub(crate) fn propagate(&mut self, hidden: &[f32]) -> Vec<f32> {
+        assert_eq!(hidden.len(), self.inputs, "скрытый слой не совпадает с пластичным слоем");
+        self.pre.clear();
+        self.pre.push(1.0);
+        self.pre.extend_from_slice(hidden);
+        let response: Vec<f32> = self
+            .weights
+            .chunks_exact(self.inputs + 1)
+            .map(|row| self.activation.apply(row.iter().zip(&self.pre).map(|(w, x)| w * x).sum()))
+            .collect();
+        //как в brain_motion: выход 0..1 со знаком относительно середины
+        for (post, output) in self.post.iter_mut().zip(&response) {
+            *post = output.clamp(0.0, 1.0) - 0.5;
+        }
+        response
+    }
+    /// Изменение весов по входам и выходам последнего прохода.
+    /// modulation - поела ли птичка на этом шаге (1 или 0), для Plasticity::Modulated
+    pub(crate) fn learn(&mut self, modulation: f32) {
+        for (i, &y) in self.post.iter().enumerate() {
+            for (j, &x) in self.pre.iter().enumerate() {
+                let k = i * (self.inputs + 1) + j;
+                let change = match self.rule {
+                    Plasticity::None => 0.0,
+                    Plasticity::Hebbian => y * x,
+                    Plasticity::Oja => y * (x - y * self.weights[k]),
+                    Plasticity::Modulated => modulation * y * x,
+                };
+                self.weights[k] =
+                    (self.weights[k] + self.rates[k] * change).clamp(-PLASTIC_LIMIT, PLASTIC_LIMIT);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    fn layer(rule: Plasticity, inheritance: Inheritance) -> (Config, PlasticLayer) {
+        let config = Config {
+            eye_cells: 1,
+            eye_animals: false,
+            brain_hidden: vec![BrainLayer { neurons: 1, activation: BrainActivation::Relu }],
+            brain_plasticity: rule,
+            brain_inheritance: inheritance,
+            ..Default::default()
+        };
+        //1 вход и 2 выхода: (смещение, вес) 0.4, 0.1 и 0.0, -0.1, скорости обучения 0.5
+        let genes: ga::Chromosome =
+            vec![0.4, 0.1, 0.0, -0.1, 0.5, 0.5, 0.5, 0.5].into_iter().collect();
+        let layer = PlasticLayer::from_chromosome(&config, 1, &genes);
+        (config, layer)
+    }
+
+    #[test_case(Plasticity::Hebbian, 0.0, 0.1 + 0.5 * 0.1 * 2.0; "hebbian")]
+    #[test_case(Plasticity::Oja, 0.0, 0.1 + 0.5 * 0.1 * (2.0 - 0.1 * 0.1); "oja")]
+    #[test_case(Plasticity::Modulated, 0.0, 0.1; "modulated hungry")]
+    #[test_case(Plasticity::Modulated, 1.0, 0.1 + 0.5 * 0.1 * 2.0; "modulated fed")]
+    fn learns_by_rule(rule: Plasticity, modulation: f32, expected: f32) {
+        let (_, mut layer) = layer(rule, Inheritance::Darwinian);
+        //выходы relu(0.4 + 0.1 * 2.0) = 0.6 и relu(-0.1 * 2.0) = 0,
+        //относительно середины 0.1 и -0.5
+        let response = layer.propagate(&[2.0]);
+        approx::assert_relative_eq!(response[0], 0.6);
+        assert_eq!(response[1], 0.0);
+
+        layer.learn(modulation);
+        approx::assert_relative_eq!(layer.weights()[1], expected);
+    }
+
+    #[test]
+    fn weights_are_limited() {
+        let (_, mut layer) = layer(Plasticity::Hebbian, Inheritance::Darwinian);
+        for _ in 0..1000 {
+            layer.propagate(&[1.0]);
+            layer.learn(0.0);
+        }
+        assert!(layer.weights().iter().all(|w| w.abs() <= PLASTIC_LIMIT));
+    }
+
+    #[test]
+    fn resizes_to_hidden_layer() {
+        let (config, layer) = layer(Plasticity::Hebbian, Inheritance::Darwinian);
+        //мутация добавила нейрон в последний скрытый слой: его связи начинаются с нуля
+        let wider = PlasticLayer::from_chromosome(&config, 2, &layer.as_chromosome());
+        assert_eq!(wider.weights(), &[0.4, 0.1, 0.0, 0.0, -0.1, 0.0]);
+        assert_eq!(wider.size(), 2 * 3);
+    }
+
+    #[test_case(Inheritance::Darwinian, 0.1; "darwinian")]
+    #[test_case(Inheritance::Lamarckian, 0.3; "lamarckian")]
+    fn inherits_weights(inheritance: Inheritance, expected: f32) {
+        let (config, mut layer) = layer(Plasticity::Hebbian, inheritance);
+        layer.set_weights(vec![0.4, 0.3, 0.0, -0.3]);
+        let genes = layer.as_chromosome();
+        assert_eq!(genes.len(), PlasticLayer::genes(&config));
+        let child = PlasticLayer::from_chromosome(&config, 1, &genes);
+        approx::assert_relative_eq!(child.weights()[1], expected);
+    }
+}
@@ -0,0 +1,92 @@
+use crate::*;
+
+/// Написанный вручную мозг для сравнения с обучаемыми: поворачивает к сегменту
+/// с наибольшей концентрацией еды и разгоняется, когда еда видна. Генов нет
+#[derive(Debug)]
+pub struct ScriptedBrain {
+    cells: usize,//кол. сегментов зрения
+    fov_angle: f32,//угол обзора
+    eye_food: bool,//первые cells входов - концентрация еды
+    signals: usize,//кол. каналов сигнала (всегда молчит)
+    speed_accel: f32,
+    rotation_accel: f32,
+}
+
+impl Brain for ScriptedBrain {
+    fn random(config: &Config, _rng: &mut dyn RngCore) -> Self {
+        Self::new(config)
+    }
+    fn from_chromosomes(config: &Config, chromosomes: Vec<ga::Chromosome>) -> Result<Self, BrainError> {
+        if !chromosomes.is_empty() {//у ScriptedBrain нет хромосом
+            return Err(BrainError::Chromosomes { found: chromosomes.len(), expected: 0 });
+        }
+        Ok(Self::new(config))
+    }
+    fn propagate(&self, vision: Vec<f32>) -> (f32, f32, Vec<f32>) {
+        let signal = vec![0.0; self.signals];
+        //сегмент с наибольшей концентрацией еды
+        let food = if self.eye_food { &vision[..self.cells] } else { &[][..] };
+        let best = food
+            .iter()
+            .enumerate()
+            .filter(|&(_, &con)| con > 0.0)
+            .max_by(|a, b| a.1.total_cmp(b.1));
+        match best {
+            //поворот к середине сегмента (сегмент 0 - правый край обзора), разгон
+            Some((cell, _)) => {
+                let offset = (cell as f32 + 0.5) / self.cells as f32 - 0.5;
+                let rotation = (offset * self.fov_angle)
+                    .clamp(-self.rotation_accel, self.rotation_accel);
+                (self.speed_accel, rotation, signal)
+            }
+            //еды не видно - торможение и поиск поворотом
+            None => (-self.speed_accel, self.rotation_accel, signal),
+        }
+    }
+    fn as_chromosomes(&self) -> Vec<ga::Chromosome> {
+        Vec::new()
+    }
+    fn size(&self) -> usize {
+        0
+    }
+}
+
+impl ScriptedBrain {
+    fn new(config: &Config) -> Self {
+        Self {
+            cells: config.eye_cells,
+            fov_angle: config.eye_fov_angle,
+            //у лучей первые cells входов - близость еды
+            eye_food: config.eye_food || config.eye_mode == EyeMode::Rays,
+            signals: config.brain_signals(),
+            speed_accel: config.sim_speed_accel,
+            rotation_accel: config.sim_rotation_accel,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn turns_towards_food() {
+        let config = Config { eye_cells: 5, ..Default::default() };
+        let brain = ScriptedBrain::new(&config);
+        //еда слева (последний сегмент) - поворот против часовой стрелки
+        let mut vision = vec![0.0; config.brain_inputs()];
+        vision[4] = 0.7;
+        let (speed, rotation, _) = brain.propagate(vision.clone());
+        assert_eq!(speed, config.sim_speed_accel);
+        assert!(rotation > 0.0);
+        //еда справа - поворот по часовой стрелке
+        vision[0] = 0.9;
+        let (_, rotation, _) = brain.propagate(vision);
+        assert!(rotation < 0.0);
+        //еда прямо - без поворота
+        let mut vision = vec![0.0; config.brain_inputs()];
+        vision[2] = 0.5;
+        let (_, rotation, _) = brain.propagate(vision);
+        assert_eq!(rotation, 0.0);
+    }
+}
@@ -0,0 +1,503 @@
+use crate::*;
+use std::path::Path;
+use std::{fs, io};
+
+/// Версия формата снимка, увеличивается при несовместимых изменениях
+pub const SNAPSHOT_VERSION: u32 = 18;
+/// Заголовок двоичного снимка (за ним версия u32 little-endian и данные bincode)
+const SNAPSHOT_MAGIC: &[u8; 8] = b"SIMSNAP\0";
+
+/// Формат файла снимка
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SnapshotFormat {
+    /// читаемый JSON
+    Json,
+    /// компактный bincode
+    Binary,
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    /// конфигурация снимка не прошла проверку
+    Config(ConfigError),
+    /// хромосомы птички не подходят к конфигурации снимка
+    Brain(BrainError),
+    /// вид птички вне видов конфигурации снимка
+    Species { found: usize, count: usize },
+    /// размер памяти или выученных весов птички не совпадает с её мозгом
+    BrainState { field: &'static str, found: usize, expected: usize },
+    Json(serde_json::Error),
+    Binary(bincode::Error),
+    /// двоичный файл не начинается с SNAPSHOT_MAGIC
+    BadMagic,
+    /// снимок сохранен другой версией формата
+    Version { found: u32, expected: u32 },
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "ошибка ввода-вывода снимка: {}", err),
+            Self::Config(err) => write!(f, "неверная конфигурация снимка: {}", err),
+            Self::Brain(err) => write!(f, "неверный мозг птички в снимке: {}", err),
+            Self::Species { found, count } => {
+                write!(f, "вид птички {} в снимке, а видов в конфигурации {}", found, count)
+            }
+            Self::BrainState { field, found, expected } => {
+                write!(f, "размер {} птички в снимке {}, ожидается {}", field, found, expected)
+            }
+            Self::Json(err) => write!(f, "ошибка JSON снимка: {}", err),
+            Self::Binary(err) => write!(f, "ошибка двоичного снимка: {}", err),
+            Self::BadMagic => write!(f, "файл не является двоичным снимком симуляции"),
+            Self::Version { found, expected } => {
+                write!(f, "версия снимка {} не поддерживается (ожидается {})", found, expected)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<io::Error> for SnapshotError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<ConfigError> for SnapshotError {
+    fn from(err: ConfigError) -> Self {
+        Self::Config(err)
+    }
+}
+
+impl From<BrainError> for SnapshotError {
+    fn from(err: BrainError) -> Self {
+        Self::Brain(err)
+    }
+}
+
+impl From<serde_json::Error> for SnapshotError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err)
+    }
+}
+
+impl From<bincode::Error> for SnapshotError {
+    fn from(err: bincode::Error) -> Self {
+        Self::Binary(err)
+    }
+}
+
+/// Полное состояние симуляции, из которого она продолжается с того же места
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    pub config: Config,
+    pub generation: usize,
+    pub age: usize,
+    deaths: Vec<usize>,
+    next_id: u64,
+    lineage: Lineage,
+    rng: ChaCha8Rng,
+    animals: Vec<AnimalSnapshot>,
+    foods: Vec<FoodSnapshot>,
+    food_patches: Vec<FoodPatchSnapshot>,
+    food_time: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct AnimalSnapshot {
+    id: AnimalId,
+    species: usize,
+    generation_lifetime: usize,
+    generation_age: usize,
+    position: [f32; 2],
+    rotation: f32,
+    speed: f32,
+    satiation: usize,
+    hunger: usize,
+    satiation_avg: f32,
+    fitness_avg: f32,
+    energy: f32,
+    alive: bool,
+    lived: usize,
+    steps: usize,
+    signal: Vec<f32>,
+    /// хромосома (веса) каждой сети мозга
+    brain: Vec<Vec<f32>>,
+    /// память мозга с прошлого шага
+    memory: Vec<f32>,
+    /// выученные веса пластичного слоя
+    learned: Vec<f32>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct FoodSnapshot {
+    position: [f32; 2],
+    age: usize,
+    regrow: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct FoodPatchSnapshot {
+    center: [f32; 2],
+    heading: f32,
+    phase: f32,
+}
+
+impl Snapshot {
+    pub fn to_json(&self) -> Result<String, SnapshotError> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, SnapshotError> {
+        //версию проверяем до разбора остальных полей
+        #[derive(Deserialize)]
+        struct Version {
+            version: u32,
+        }
+        let Version { version } = serde_json::from_str(json)?;
+        check_version(version)?;
+
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SnapshotError> {
+        let mut bytes = SNAPSHOT_MAGIC.to_vec();
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        bincode::serialize_into(&mut bytes, self)?;
+        Ok(bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        let header = SNAPSHOT_MAGIC.len();
+        if bytes.len() < header + 4 || bytes[..header] != SNAPSHOT_MAGIC[..] {
+            return Err(SnapshotError::BadMagic);
+        }
+        let mut version = [0u8; 4];
+        version.copy_from_slice(&bytes[header..header + 4]);
+        check_version(u32::from_le_bytes(version))?;
+
+        Ok(bincode::deserialize(&bytes[header + 4..])?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>, format: SnapshotFormat) -> Result<(), SnapshotError> {
+        let bytes = match format {
+            SnapshotFormat::Json => self.to_json()?.into_bytes(),
+            SnapshotFormat::Binary => self.to_bytes()?,
+        };
+        fs::write(path, bytes)?;
+        Ok(())
+    }
+    /// Формат определяется по заголовку файла
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SnapshotError> {
+        let bytes = fs::read(path)?;
+        if bytes.starts_with(SNAPSHOT_MAGIC) {
+            Self::from_bytes(&bytes)
+        } else {
+            let json = String::from_utf8(bytes)
+                .map_err(|err| SnapshotError::Io(io::Error::new(io::ErrorKind::InvalidData, err)))?;
+            Self::from_json(&json)
+        }
+    }
+}
+
+fn check_version(found: u32) -> Result<(), SnapshotError> {
+    if found == SNAPSHOT_VERSION {
+        Ok(())
+    } else {
+        Err(SnapshotError::Version { found, expected: SNAPSHOT_VERSION })
+    }
+}
+
+impl Simulation {
+    /// Снимок всего состояния симуляции
+    pub fn snapshot(&self) -> Snapshot {
+        let animals = self
+            .world
+            .animals
+            .iter()
+            .map(|animal| AnimalSnapshot {
+                id: animal.id,
+                species: animal.species,
+                generation_lifetime: animal.generation_lifetime,
+                generation_age: animal.generation_age,
+                position: [animal.position.x, animal.position.y],
+                rotation: animal.rotation.angle(),
+                speed: animal.speed,
+                satiation: animal.satiation,
+                hunger: animal.hunger,
+                satiation_avg: animal.satiation_avg,
+                fitness_avg: animal.fitness_avg,
+                energy: animal.energy,
+                alive: animal.alive,
+                lived: animal.lived,
+                steps: animal.steps,
+                signal: animal.signal.clone(),
+                brain: animal
+                    .as_chromosomes()
+                    .iter()
+                    .map(|c| c.iter().copied().collect())
+                    .collect(),
+                memory: animal.brain.memory(),
+                learned: animal.brain.learned(),
+            })
+            .collect();
+
+        let foods = self
+            .world
+            .foods
+            .iter()
+            .map(|food| FoodSnapshot {
+                position: [food.position.x, food.position.y],
+                age: food.age,
+                regrow: food.regrow,
+            })
+            .collect();
+
+        let food_patches = self
+            .world
+            .food_patches
+            .patches
+            .iter()
+            .map(|patch| FoodPatchSnapshot {
+                center: [patch.center.x, patch.center.y],
+                heading: patch.heading,
+                phase: patch.phase,
+            })
+            .collect();
+
+        Snapshot {
+            version: SNAPSHOT_VERSION,
+            config: self.config.clone(),
+            generation: self.generation,
+            age: self.age,
+            deaths: self.deaths.clone(),
+            next_id: self.world.next_id,
+            lineage: self.lineage.clone(),
+            rng: self.rng.clone(),
+            animals,
+            foods,
+            food_patches,
+            food_time: self.world.food_patches.time,
+        }
+    }
+    /// Симуляция, продолжающаяся ровно с места снимка
+    pub fn restore(snapshot: Snapshot) -> Result<Self, SnapshotError> {
+        check_version(snapshot.version)?;
+        snapshot.config.validate()?;
+        let config = snapshot.config;
+        let species = config.species_configs();
+        //генератор для конструктора птички, все случайные поля затем перезаписываются
+        let mut scratch = ChaCha8Rng::seed_from_u64(0);
+
+        let animals = snapshot
+            .animals
+            .into_iter()
+            .map(|a| {
+                let chromosomes: Vec<ga::Chromosome> =
+                    a.brain.into_iter().map(|w| w.into_iter().collect()).collect();
+                let config = &species
+                    .get(a.species)
+                    .ok_or(SnapshotError::Species { found: a.species, count: species.len() })?
+                    .config;
+                let mut animal = Animal::from_chromosome(config, &mut scratch, chromosomes, a.id)?;
+                animal.species = a.species;
+                animal.generation_lifetime = a.generation_lifetime;
+                animal.generation_age = a.generation_age;
+                animal.position = na::Point2::new(a.position[0], a.position[1]);
+                animal.rotation = na::Rotation2::new(a.rotation);
+                animal.speed = a.speed;
+                animal.satiation = a.satiation;
+                animal.hunger = a.hunger;
+                animal.satiation_avg = a.satiation_avg;
+                animal.fitness_avg = a.fitness_avg;
+                animal.energy = a.energy;
+                animal.alive = a.alive;
+                animal.lived = a.lived;
+                animal.steps = a.steps;
+                animal.signal = a.signal;
+                //память и выученные веса должны подходить к мозгу из хромосом
+                let memory = animal.brain.memory().len();
+                if a.memory.len() != memory {
+                    return Err(SnapshotError::BrainState {
+                        field: "memory",
+                        found: a.memory.len(),
+                        expected: memory,
+                    });
+                }
+                let learned = animal.brain.learned().len();
+                if a.learned.len() != learned {
+                    return Err(SnapshotError::BrainState {
+                        field: "learned",
+                        found: a.learned.len(),
+                        expected: learned,
+                    });
+                }
+                animal.brain.set_memory(a.memory);
+                animal.brain.set_learned(a.learned);
+                Ok(animal)
+            })
+            .collect::<Result<Vec<Animal>, SnapshotError>>()?;
+
+        let foods = snapshot
+            .foods
+            .into_iter()
+            .map(|f| Food {
+                position: na::Point2::new(f.position[0], f.position[1]),
+                age: f.age,
+                regrow: f.regrow,
+            })
+            .collect();
+
+        let food_patches = FoodPatches {
+            patches: snapshot
+                .food_patches
+                .into_iter()
+                .map(|p| FoodPatch {
+                    center: na::Point2::new(p.center[0], p.center[1]),
+                    heading: p.heading,
+                    phase: p.phase,
+                })
+                .collect(),
+            time: snapshot.food_time,
+        };
+
+        let world = World::new(&config, animals, foods, food_patches, snapshot.next_id);
+
+        Ok(Self {
+            rng: snapshot.rng,
+            config,
+            species,
+            world,
+            generation: snapshot.generation,
+            age: snapshot.age,
+            deaths: snapshot.deaths,
+            lineage: snapshot.lineage,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resumes_exactly(restore: impl Fn(Snapshot) -> Snapshot) {
+        resumes_exactly_with(Default::default(), restore);
+    }
+
+    fn resumes_exactly_with(config: Config, restore: impl Fn(Snapshot) -> Snapshot) {
+        let mut sim = Simulation::from_seed(config, 7).unwrap();
+        sim.train().unwrap();
+        for _ in 0..100 {
+            sim.step().unwrap();
+        }
+        let mut restored = Simulation::restore(restore(sim.snapshot())).unwrap();
+
+        let expected = sim.train().unwrap();
+        let actual = restored.train().unwrap();
+        assert_eq!(expected.generation, actual.generation);
+        assert_eq!(expected.to_records(), actual.to_records());
+        assert_eq!(format!("{}", expected), format!("{}", actual));
+    }
+
+    #[test]
+    fn json_roundtrip() {
+        resumes_exactly(|s| Snapshot::from_json(&s.to_json().unwrap()).unwrap());
+    }
+
+    #[test]
+    fn binary_roundtrip() {
+        resumes_exactly(|s| Snapshot::from_bytes(&s.to_bytes().unwrap()).unwrap());
+    }
+
+    #[test]
+    fn food_ecology_roundtrip() {
+        let config = Config::preset("food-patches").unwrap();
+        resumes_exactly_with(config, |s| Snapshot::from_bytes(&s.to_bytes().unwrap()).unwrap());
+    }
+
+    #[test]
+    fn predators_roundtrip() {
+        let config = Config::preset("predators").unwrap();
+        resumes_exactly_with(config, |s| Snapshot::from_json(&s.to_json().unwrap()).unwrap());
+    }
+
+    #[test]
+    fn memory_roundtrip() {
+        let config = Config { brain_memory: 2, ..Default::default() };
+        resumes_exactly_with(config, |s| Snapshot::from_bytes(&s.to_bytes().unwrap()).unwrap());
+    }
+
+    #[test]
+    fn plasticity_roundtrip() {
+        let config = Config { brain_plasticity: Plasticity::Oja, ..Default::default() };
+        resumes_exactly_with(config, |s| Snapshot::from_json(&s.to_json().unwrap()).unwrap());
+    }
+
+    #[test]
+    fn rejects_brain_of_other_config() {
+        let mut snapshot = Simulation::from_seed(Default::default(), 7).unwrap().snapshot();
+        //сети птичек видят 9 сегментов, а конфигурация - 5
+        snapshot.config.eye_cells = 5;
+        assert!(matches!(
+            Simulation::restore(snapshot),
+            Err(SnapshotError::Brain(BrainError::Inputs { nv_num: 0, .. }))
+        ));
+    }
+
+    #[test]
+    fn rejects_malformed_animals() {
+        let config = Config {
+            brain_memory: 2,
+            brain_plasticity: Plasticity::Hebbian,
+            ..Default::default()
+        };
+        let snapshot = Simulation::from_seed(config, 7).unwrap().snapshot();
+
+        let mut other = snapshot.clone();
+        other.animals[0].species = 1;
+        assert!(matches!(
+            Simulation::restore(other),
+            Err(SnapshotError::Species { found: 1, count: 1 })
+        ));
+        let mut other = snapshot.clone();
+        other.animals[0].memory.push(0.0);
+        assert!(matches!(
+            Simulation::restore(other),
+            Err(SnapshotError::BrainState { field: "memory", found: 3, expected: 2 })
+        ));
+        //обрезанный и пустой пластичный слой (хромосома после сети намерения)
+        for genes in [1, 0] {
+            let mut other = snapshot.clone();
+            other.animals[0].brain[1].truncate(genes);
+            assert!(matches!(
+                Simulation::restore(other),
+                Err(SnapshotError::Brain(BrainError::Plastic { found, .. })) if found == genes
+            ));
+        }
+        let mut other = snapshot;
+        other.animals[0].learned.clear();
+        assert!(matches!(
+            Simulation::restore(other),
+            Err(SnapshotError::BrainState { field: "learned", found: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_other_version() {
+        let mut snapshot = Simulation::from_seed(Default::default(), 7).unwrap().snapshot();
+        snapshot.version = SNAPSHOT_VERSION + 1;
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert!(matches!(
+            Snapshot::from_json(&json),
+            Err(SnapshotError::Version { .. })
+        ));
+        assert!(matches!(
+            Snapshot::from_bytes(b"not a snapshot"),
+            Err(SnapshotError::BadMagic)
+        ));
+    }
+}
@@ -0,0 +1,159 @@
+use crate::*;
+
+/// Мозг на нейросетях nn::SoftNetwork: топология из конфигурации (кол. нейронов
+/// config.brain_hidden, функции активации задает сама SoftNetwork)
+#[derive(Debug)]
+pub struct SoftBrain {
+    inputs: usize,//кол. входов сети намерения
+    motive_inputs: usize,//кол. входов двигательной сети
+    hearing: std::ops::Range<usize>,//каналы слуха в векторе чувств (входы и двигательной сети)
+    intentions: usize,//кол. намерений, передаваемых от сети намерения двигательной сети
+    size: usize,//кол. весов всех сетей (для расхода энергии)
+    layers: Vec<Vec<usize>>,//кол. нейронов по слоям каждой сети (топология из конфигурации)
+    speed_accel: f32,
+    rotation_accel: f32,
+    nv: Vec<nn::SoftNetwork>,//0 - сеть намерения (intention), 1 - двигательная (motive) сеть
+}
+
+impl Brain for SoftBrain {
+    //сети с топологией из конфигурации, но случайными весами
+    fn random(config: &Config, rng: &mut dyn RngCore) -> Self {
+        let mut nv: Vec<nn::SoftNetwork> = Vec::new();
+        nv.push(nn::SoftNetwork::random(rng, &Self::topology_i(config)));
+        if config.brain_motive {
+            nv.push(nn::SoftNetwork::random(rng, &Self::topology_m(config)));
+        }
+        Self::new(config, nv)
+    }
+    fn from_chromosomes(config: &Config, chromosomes: Vec<ga::Chromosome>) -> Result<Self, BrainError> {
+        if chromosomes.len() != config.brain_networks() {
+            return Err(BrainError::Chromosomes {
+                found: chromosomes.len(),
+                expected: config.brain_networks(),
+            });
+        }
+        //в хромосоме только веса: их кол. должно совпасть с топологией из конфигурации
+        let topologies = [Self::topology_i(config), Self::topology_m(config)];
+        for (nv_num, (chromosome, topology)) in chromosomes.iter().zip(&topologies).enumerate() {
+            let expected = Self::weights_of(topology);
+            if chromosome.len() != expected {
+                return Err(BrainError::Weights { nv_num, found: chromosome.len(), expected });
+            }
+        }
+        let nv: Vec<nn::SoftNetwork> = chromosomes
+            .iter()
+            .map(|c| nn::SoftNetwork::from_weights(c.iter()))
+            .collect();
+        Ok(Self::new(config, nv))
+    }
+    //сеть намерения (intention), а если есть - и двигательная (motive) сеть
+    fn propagate(&self, vision: Vec<f32>) -> (f32, f32, Vec<f32>) {
+        assert_eq!(vision.len(), self.inputs, "вектор чувств не совпадает с входами сети");
+        let hearing = vision[self.hearing.clone()].to_vec();
+        let response = self.nv[0].propagate(vision);
+        let (speed, rotation) = brain_motion(&response, self.speed_accel, self.rotation_accel);
+        //получаем намерения в один срез, а в другой срез - сигнал
+        let (msgs, signal) = response[2..].split_at(self.intentions);
+        let signal = signal.iter().map(|s| s.clamp(0.0, 1.0)).collect();
+        if self.nv.len() == 1 {
+            return (speed, rotation, signal);
+        }
+        //двигательная (motive) сеть
+        let mut vision: Vec<f32> = vec![speed, rotation];
+        vision.extend_from_slice(msgs);
+        vision.extend(hearing);
+        assert_eq!(vision.len(), self.motive_inputs, "вход не совпадает с двигательной сетью");
+        let response = self.nv[1].propagate(vision);
+        let (speed, rotation) = brain_motion(&response, self.speed_accel, self.rotation_accel);
+
+        (speed, rotation, signal)
+    }
+    //выбранная по номеру сеть как хромосома
+    fn as_chromosome(&self, nv_num: usize) -> ga::Chromosome {
+        self.nv[nv_num].weights().collect()
+    }
+    fn as_chromosomes(&self) -> Vec<ga::Chromosome> {
+        (0..self.nv.len()).map(|n| self.as_chromosome(n)).collect()
+    }
+    fn size(&self) -> usize {
+        self.size
+    }
+    fn neurons_by_layer(&self, nv_num: usize) -> Vec<usize> {
+        self.layers[nv_num].clone()
+    }
+}
+
+impl SoftBrain {
+    fn new(config: &Config, nv: Vec<nn::SoftNetwork>) -> Self {
+        let size = nv.iter().map(|n| n.weights().count()).sum();
+        Self {
+            inputs: config.brain_inputs(),
+            motive_inputs: config.motive_inputs(),
+            hearing: config.hearing(),
+            intentions: if config.brain_motive { config.brain_intentions } else { 0 },
+            size,
+            layers: [Self::topology_i(config), Self::topology_m(config)]
+                .iter()
+                .take(nv.len())
+                .map(|topology| topology.iter().map(|l| l.neurons).collect())
+                .collect(),
+            speed_accel: config.sim_speed_accel,
+            rotation_accel: config.sim_rotation_accel,
+            nv,//0 - сеть намерения (intention), 1 - двигательная (motive) сеть
+        }
+    }
+    //сеть намерения (intention): чувства -> скорость и поворот, намерения, сигнал
+    fn topology_i(config: &Config) -> Vec<nn::SoftLayerTopology> {
+        let mut layers: Vec<usize> = config.brain_hidden.iter().map(|l| l.neurons).collect();
+        let intentions = if config.brain_motive { config.brain_intentions } else { 0 };
+        layers.push(2 + intentions + config.brain_signals());
+        Self::topology(config.brain_inputs(), &layers)
+    }
+    //двигательная (motive) сеть: скорость и поворот, намерения, слух -> скорость и поворот
+    fn topology_m(config: &Config) -> Vec<nn::SoftLayerTopology> {
+        let mut layers: Vec<usize> = config.brain_motive_hidden.iter().map(|l| l.neurons).collect();
+        layers.push(2);
+        Self::topology(config.motive_inputs(), &layers)
+    }
+    //кол. весов сети с топологией topology (веса случайной сети не важны)
+    fn weights_of(topology: &[nn::SoftLayerTopology]) -> usize {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        nn::SoftNetwork::random(&mut rng, topology).weights().count()
+    }
+    //входной слой передает каждый вход на свой нейрон, каждый следующий слой
+    //полностью связан с предыдущим. Нейроны нумеруются подряд с 1: (нейрон, вх.связь)
+    fn topology(inputs: usize, layers: &[usize]) -> Vec<nn::SoftLayerTopology> {
+        let mut topology = vec![nn::SoftLayerTopology {
+            neurons: inputs,
+            connections: (1..=inputs).map(|i| (i, i)).collect(),
+        }];
+        let mut prev = 1..=inputs;
+        for &neurons in layers {
+            let first = prev.end() + 1;
+            let current = first..=prev.end() + neurons;
+            let mut connections: Vec<(usize, usize)> = Vec::new();
+            for i in current.clone() {
+                for j in prev.clone() {
+                    connections.push((i, j));
+                }
+            }
+            topology.push(nn::SoftLayerTopology { neurons, connections });
+            prev = current;
+        }
+        topology
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn topology_numbers_neurons_in_a_row() {
+        let topology = SoftBrain::topology(3, &[2, 1]);
+        assert_eq!(topology.len(), 3);
+        assert_eq!(topology[0].connections, vec![(1, 1), (2, 2), (3, 3)]);
+        assert_eq!(topology[1].connections, vec![(4, 1), (4, 2), (4, 3), (5, 1), (5, 2), (5, 3)]);
+        assert_eq!(topology[2].connections, vec![(6, 4), (6, 5)]);
+    }
+}
@@ -0,0 +1,127 @@
+use crate::*;
+
+/// Вид птичек (секция config.species): заданные поля заменяют общие поля Config
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Species {
+    pub name: String,
+    pub animals: usize,// кол. птичек вида на карте
+    pub diet: Diet,// чем питается вид
+    pub sim_speed_min: Option<f32>,
+    pub sim_speed_max: Option<f32>,
+    pub sim_speed_accel: Option<f32>,
+    pub sim_rotation_accel: Option<f32>,
+    pub eye_mode: Option<EyeMode>,
+    pub eye_fov_range: Option<f32>,
+    pub eye_fov_angle: Option<f32>,
+    pub eye_cells: Option<usize>,
+    pub brain_hidden: Option<Vec<BrainLayer>>,
+}
+
+/// Чем питается вид
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Diet {
+    /// ест еду Food
+    #[default]
+    Food,
+    /// хищник: ловит птичек видов, питающихся едой (в пределах food_size), пойманная погибает
+    Prey,
+}
+
+/// Действующая конфигурация вида: общая Config с полями из его секции
+#[derive(Clone, Debug)]
+pub struct SpeciesConfig {
+    pub(crate) name: String,
+    pub(crate) diet: Diet,
+    pub(crate) config: Config,
+}
+
+impl SpeciesConfig {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn diet(&self) -> Diet {
+        self.diet
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+}
+
+impl Config {
+    /// Действующие конфигурации всех видов. Без config.species - один безымянный вид
+    /// из world_animals птичек, питающихся едой
+    pub fn species_configs(&self) -> Vec<SpeciesConfig> {
+        if self.species.is_empty() {
+            return vec![SpeciesConfig {
+                name: String::new(),
+                diet: Diet::Food,
+                config: self.clone(),
+            }];
+        }
+        self.species
+            .iter()
+            .map(|species| SpeciesConfig {
+                name: species.name.clone(),
+                diet: species.diet,
+                config: self.species_config(species),
+            })
+            .collect()
+    }
+    //общая конфигурация с заменой полей из секции вида
+    pub(crate) fn species_config(&self, species: &Species) -> Config {
+        Config {
+            brain_hidden: species.brain_hidden.clone().unwrap_or_else(|| self.brain_hidden.clone()),
+            eye_mode: species.eye_mode.unwrap_or(self.eye_mode),
+            eye_fov_range: species.eye_fov_range.unwrap_or(self.eye_fov_range),
+            eye_fov_angle: species.eye_fov_angle.unwrap_or(self.eye_fov_angle),
+            eye_cells: species.eye_cells.unwrap_or(self.eye_cells),
+            sim_speed_min: species.sim_speed_min.unwrap_or(self.sim_speed_min),
+            sim_speed_max: species.sim_speed_max.unwrap_or(self.sim_speed_max),
+            sim_speed_accel: species.sim_speed_accel.unwrap_or(self.sim_speed_accel),
+            sim_rotation_accel: species.sim_rotation_accel.unwrap_or(self.sim_rotation_accel),
+            world_animals: species.animals,
+            species: Vec::new(),
+            ..self.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn species_override_base_fields() {
+        let config = Config {
+            species: vec![
+                Species { name: "prey".into(), animals: 4, ..Default::default() },
+                Species {
+                    name: "hawk".into(),
+                    animals: 2,
+                    diet: Diet::Prey,
+                    sim_speed_max: Some(0.008),
+                    eye_cells: Some(5),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let species = config.species_configs();
+        assert_eq!(species.len(), 2);
+        assert_eq!(species[0].config.world_animals, 4);
+        assert_eq!(species[0].config.eye_cells, config.eye_cells);
+        assert_eq!(species[1].diet(), Diet::Prey);
+        assert_eq!(species[1].config.sim_speed_max, 0.008);
+        assert_eq!(species[1].config.eye_cells, 5);
+        assert_eq!(species[1].config.sim_speed_min, config.sim_speed_min);
+        assert!(species[1].config.species.is_empty());
+        //без секций - один вид из общей конфигурации
+        let single = Config::default().species_configs();
+        assert_eq!(single.len(), 1);
+        assert_eq!(single[0].config.world_animals, Config::default().world_animals);
+    }
+}
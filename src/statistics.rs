@@ -1,35 +1,400 @@
 use crate::*;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
 
 #[derive(Clone, Debug)]
 pub struct Statistics {
     pub generation: usize,
-    pub ga: Vec<ga::Statistics>,
+    /// имя вида (пусто для всей популяции)
+    pub species_name: String,
+    /// статистика ГА (None, если мозг без генов)
+    pub ga: Option<GaStatistics>,
+    /// приспособленность птичек прошлой популяции
+    pub fitness: FitnessSummary,
+    /// кол. птичек в популяции
+    pub population: usize,
+    /// сколько еды съедено за поколение
+    pub food_eaten: usize,
+    /// сколько птичек погибло от голода или поймано хищниками за поколение
+    pub deaths: usize,
+    /// статистика каждого вида (пусто, если вид один)
+    pub species: Vec<Statistics>,
+}
+
+impl Statistics {
+    /// Плоская запись поколения для CSV/JSONL
+    pub fn to_record(&self) -> StatisticsRecord {
+        //без ГА приспособленность считается по птичкам, сведений о сетях нет
+        let Some(ga) = &self.ga else {
+            return StatisticsRecord {
+                generation: self.generation,
+                min_fitness: self.fitness.min,
+                max_fitness: self.fitness.max,
+                avg_fitness: self.fitness.avg,
+                median_fitness: self.fitness.median,
+                changed_count: 0,
+                neuron_count: 0,
+                neurons_by_layer: json!([]).to_string(),
+                population: self.population,
+                food_eaten: self.food_eaten,
+                deaths: self.deaths,
+                species_name: self.species_name.clone(),
+            };
+        };
+        StatisticsRecord {
+            generation: self.generation,
+            min_fitness: ga.min_fitness(),
+            max_fitness: ga.max_fitness(),
+            avg_fitness: ga.avg_fitness(),
+            median_fitness: ga.median_fitness(),
+            changed_count: ga.changed_count(),
+            neuron_count: ga.neuron_count(),
+            neurons_by_layer: json!(ga.networks()).to_string(),
+            population: self.population,
+            food_eaten: self.food_eaten,
+            deaths: self.deaths,
+            species_name: self.species_name.clone(),
+        }
+    }
+    /// Записи для таблицы: по одной на каждый вид или одна на всю популяцию
+    pub fn to_records(&self) -> Vec<StatisticsRecord> {
+        if self.species.is_empty() {
+            vec![self.to_record()]
+        } else {
+            self.species.iter().map(|s| s.to_record()).collect()
+        }
+    }
+}
+
+/// Статистика ГА за поколение: общая для всех сетей мозга (сети потомка заменяются вместе)
+/// и топология каждой сети
+#[derive(Clone, Debug, PartialEq)]
+pub struct GaStatistics {
+    /// приспособленность прошлой популяции
+    pub(crate) fitness: FitnessSummary,
+    /// кол. птичек, замененных потомками
+    pub(crate) changed_count: usize,
+    /// кол. нейронов по слоям каждой сети лучшей птички
+    pub(crate) networks: Vec<Vec<usize>>,
+}
+
+impl GaStatistics {
+    pub fn min_fitness(&self) -> f32 {
+        self.fitness.min
+    }
+
+    pub fn max_fitness(&self) -> f32 {
+        self.fitness.max
+    }
+
+    pub fn avg_fitness(&self) -> f32 {
+        self.fitness.avg
+    }
+
+    pub fn median_fitness(&self) -> f32 {
+        self.fitness.median
+    }
+
+    pub fn changed_count(&self) -> usize {
+        self.changed_count
+    }
+    /// Всего нейронов в сети намерения лучшей птички: по всем слоям, включая входной
+    pub fn neuron_count(&self) -> usize {
+        self.neurons_by_layer(0).iter().sum()
+    }
+    /// Кол. нейронов по слоям сети номер nv_num
+    pub fn neurons_by_layer(&self, nv_num: usize) -> &[usize] {
+        &self.networks[nv_num]
+    }
+    /// Кол. нейронов по слоям каждой сети
+    pub fn networks(&self) -> &[Vec<usize>] {
+        &self.networks
+    }
+}
+
+/// Приспособленность популяции: минимум, максимум, среднее и медиана
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FitnessSummary {
+    pub min: f32,
+    pub max: f32,
+    pub avg: f32,
+    pub median: f32,
+}
+
+impl FitnessSummary {
+    pub fn new(values: impl IntoIterator<Item = f32>) -> Self {
+        let mut values: Vec<f32> = values.into_iter().collect();
+        if values.is_empty() {
+            return Self::default();
+        }
+        values.sort_by(|a, b| a.total_cmp(b));
+        let len = values.len();
+        let median = if len % 2 == 0 {
+            (values[len / 2 - 1] + values[len / 2]) / 2.0
+        } else {
+            values[len / 2]
+        };
+        Self {
+            min: values[0],
+            max: values[len - 1],
+            avg: values.iter().sum::<f32>() / len as f32,
+            median,
+        }
+    }
 }
 
 impl fmt::Display for Statistics {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "Поколение {}:", self.generation)?;
-        writeln!(f, "Макс.Нейрон: {}", self.ga[0].max_neuron_num())?;
-        writeln!(f, "Сети: {}", json!(&self.ga[0].neurons_by_layer()))?;
+        if self.species.is_empty() {
+            return self.fmt_population(f);
+        }
+        for (k, species) in self.species.iter().enumerate() {
+            if k > 0 {
+                writeln!(f)?;
+            }
+            writeln!(
+                f,
+                "Вид {} ({} птичек, погибло {}):",
+                species.species_name,
+                species.population,
+                species.deaths
+            )?;
+            species.fmt_population(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl Statistics {
+    //статистика одной популяции ГА (без заголовка поколения)
+    fn fmt_population(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(ga) = &self.ga else {
+            //мозг без генов - только приспособленность птичек
+            return write!(
+                f,
+                "min[{:.2}] max[{:.2}] avg[{:.2}] median[{:.2}];",
+                self.fitness.min,
+                self.fitness.max,
+                self.fitness.avg,
+                self.fitness.median
+            );
+        };
+        writeln!(f, "Нейронов: {}", ga.neuron_count())?;
+        //сеть намерения, за ней двигательная сеть
+        writeln!(f, "Сети: {}", json!(ga.networks()))?;
         write!(
             f,
-            "min[{:.2}] max[{:.2}] avg[{:.2}] median[{:.2}] изм[{:.2}];",
-            &self.ga[0].min_fitness(),
-            &self.ga[0].max_fitness(),
-            &self.ga[0].avg_fitness(),
-            &self.ga[0].median_fitness(),
-            &self.ga[0].changed_count()
-        )?;
-
-        // for ga1 in &self.ga {
-        //     write!(
-        //         f,
-        //         "min[{:.2}] max[{:.2}] avg[{:.2}] median[{:.2}]; ",
-        //         ga1.min_fitness(),
-        //         ga1.max_fitness(),
-        //         ga1.avg_fitness(),
-        //         ga1.median_fitness())?
-        // }
-        Ok(())
+            "min[{:.2}] max[{:.2}] avg[{:.2}] median[{:.2}] изм[{}];",
+            ga.min_fitness(),
+            ga.max_fitness(),
+            ga.avg_fitness(),
+            ga.median_fitness(),
+            ga.changed_count()
+        )
+    }
+}
+
+/// Статистика одного поколения в виде строки таблицы
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StatisticsRecord {
+    pub generation: usize,
+    pub min_fitness: f32,
+    pub max_fitness: f32,
+    pub avg_fitness: f32,
+    pub median_fitness: f32,
+    /// кол. замененных птичек
+    pub changed_count: usize,
+    /// всего нейронов сети намерения, включая входной слой
+    pub neuron_count: usize,
+    /// кол. нейронов по слоям каждой сети (JSON)
+    pub neurons_by_layer: String,
+    pub population: usize,
+    pub food_eaten: usize,
+    pub deaths: usize,
+    /// имя вида (пусто, если вид один)
+    pub species_name: String,
+}
+
+impl StatisticsRecord {
+    const CSV_HEADER: &'static str = "generation,min_fitness,max_fitness,avg_fitness,\
+median_fitness,changed_count,neuron_count,neurons_by_layer,population,food_eaten,deaths,species_name";
+
+    fn to_csv(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{}",
+            self.generation,
+            self.min_fitness,
+            self.max_fitness,
+            self.avg_fitness,
+            self.median_fitness,
+            self.changed_count,
+            self.neuron_count,
+            //JSON содержит запятые и кавычки
+            csv_quote(&self.neurons_by_layer),
+            self.population,
+            self.food_eaten,
+            self.deaths,
+            csv_quote(&self.species_name),
+        )
+    }
+}
+
+//поле CSV в кавычках, кавычки внутри удваиваются
+fn csv_quote(field: &str) -> String {
+    format!("\"{}\"", field.replace('"', "\"\""))
+}
+
+/// Формат файла метрик
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MetricsFormat {
+    Csv,
+    /// JSON Lines: одна запись JSON на строку
+    Jsonl,
+}
+
+impl MetricsFormat {
+    /// Формат по расширению файла: .csv или .jsonl/.ndjson
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        match path.as_ref().extension()?.to_str()? {
+            "csv" => Some(Self::Csv),
+            "jsonl" | "ndjson" => Some(Self::Jsonl),
+            _ => None,
+        }
+    }
+}
+
+/// Построчная запись статистики поколений в CSV или JSONL
+pub struct MetricsWriter<W: Write> {
+    writer: W,
+    format: MetricsFormat,
+    /// заголовок CSV уже есть
+    header: bool,
+}
+
+impl MetricsWriter<BufWriter<File>> {
+    /// Дописывает в файл, заголовок CSV пишется только в пустой файл
+    pub fn append_to(path: impl AsRef<Path>, format: MetricsFormat) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let header = file.metadata()?.len() > 0;
+
+        Ok(Self {
+            writer: BufWriter::new(file),
+            format,
+            header,
+        })
+    }
+}
+
+impl<W: Write> MetricsWriter<W> {
+    pub fn new(writer: W, format: MetricsFormat) -> Self {
+        Self {
+            writer,
+            format,
+            header: false,
+        }
+    }
+    /// Запись одного поколения, по строке на каждый вид (сразу сбрасывается на диск)
+    pub fn append(&mut self, statistics: &Statistics) -> io::Result<()> {
+        for record in statistics.to_records() {
+            match self.format {
+                MetricsFormat::Csv => {
+                    if !self.header {
+                        writeln!(self.writer, "{}", StatisticsRecord::CSV_HEADER)?;
+                        self.header = true;
+                    }
+                    writeln!(self.writer, "{}", record.to_csv())?;
+                }
+                MetricsFormat::Jsonl => {
+                    serde_json::to_writer(&mut self.writer, &record)?;
+                    writeln!(self.writer)?;
+                }
+            }
+        }
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn statistics() -> Vec<Statistics> {
+        let mut sim = Simulation::from_seed(Config::preset("tiny-debug").unwrap(), 5).unwrap();
+        (0..2).map(|_| sim.train().unwrap()).collect()
+    }
+
+    #[test]
+    fn to_record() {
+        let statistics = &statistics()[1];
+        let record = statistics.to_record();
+
+        assert_eq!(record.generation, 1);
+        assert_eq!(record.population, 6);
+        assert_eq!(record.avg_fitness, statistics.ga.as_ref().unwrap().avg_fitness());
+        assert!(record.min_fitness <= record.median_fitness);
+        assert!(record.median_fitness <= record.max_fitness);
+    }
+
+    #[test]
+    fn neuron_count_includes_input_layer() {
+        let ga = GaStatistics {
+            fitness: FitnessSummary::default(),
+            changed_count: 0,
+            networks: vec![vec![6, 2, 11], vec![11, 11, 2]],
+        };
+        //только сеть намерения, вместе с входами
+        assert_eq!(ga.neuron_count(), 6 + 2 + 11);
+    }
+
+    #[test]
+    fn fitness_summary() {
+        let summary = FitnessSummary::new([3.0, 1.0, 4.0, 2.0]);
+        assert_eq!(summary, FitnessSummary { min: 1.0, max: 4.0, avg: 2.5, median: 2.5 });
+        assert_eq!(FitnessSummary::new([]), FitnessSummary::default());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn csv() {
+        let mut writer = MetricsWriter::new(Vec::new(), MetricsFormat::Csv);
+        for statistics in &statistics() {
+            writer.append(statistics).unwrap();
+        }
+        let csv = String::from_utf8(writer.into_inner()).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], StatisticsRecord::CSV_HEADER);
+        assert!(lines[1].starts_with("0,"));
+        assert!(lines[2].starts_with("1,"));
+    }
+
+    #[test]
+    fn jsonl() {
+        let statistics = statistics();
+        let mut writer = MetricsWriter::new(Vec::new(), MetricsFormat::Jsonl);
+        for statistics in &statistics {
+            writer.append(statistics).unwrap();
+        }
+        let jsonl = String::from_utf8(writer.into_inner()).unwrap();
+        let records: Vec<StatisticsRecord> = jsonl
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        let expected: Vec<StatisticsRecord> = statistics.iter().map(|s| s.to_record()).collect();
+        assert_eq!(records, expected);
+    }
+
+    #[test]
+    fn format_from_path() {
+        assert_eq!(MetricsFormat::from_path("out/metrics.csv"), Some(MetricsFormat::Csv));
+        assert_eq!(MetricsFormat::from_path("metrics.jsonl"), Some(MetricsFormat::Jsonl));
+        assert_eq!(MetricsFormat::from_path("metrics.txt"), None);
+    }
+}
@@ -0,0 +1,68 @@
+use crate::*;
+
+/// Топология мира: как считаются расстояния и что происходит на краю единичного квадрата
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Topology {
+    /// тор: края квадрата склеены, птичка вылетает с другой стороны
+    #[default]
+    Torus,
+    /// квадрат со стенами: птичка упирается в край
+    Walls,
+    /// бесконечная плоскость: птичка может улететь за пределы квадрата
+    Plane,
+}
+
+impl Topology {
+    /// Вектор от from до to (по кратчайшему пути для тора)
+    pub fn delta(self, from: na::Point2<f32>, to: na::Point2<f32>) -> na::Vector2<f32> {
+        let mut vec = to - from;
+        if self == Self::Torus {
+            vec.x -= vec.x.round();
+            vec.y -= vec.y.round();
+        }
+        vec
+    }
+    /// Расстояние от from до to
+    pub fn distance(self, from: na::Point2<f32>, to: na::Point2<f32>) -> f32 {
+        self.delta(from, to).norm()
+    }
+    /// Приведение позиции после перемещения
+    pub fn confine(self, position: na::Point2<f32>) -> na::Point2<f32> {
+        match self {
+            Self::Torus => na::Point2::new(
+                na::wrap(position.x, 0.0, 1.0),
+                na::wrap(position.y, 0.0, 1.0),
+            ),
+            Self::Walls => na::Point2::new(
+                position.x.clamp(0.0, 1.0),
+                position.y.clamp(0.0, 1.0),
+            ),
+            Self::Plane => position,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn torus_takes_shortest_path() {
+        let from = na::Point2::new(0.99, 0.5);
+        let to = na::Point2::new(0.01, 0.5);
+
+        approx::assert_relative_eq!(Topology::Torus.distance(from, to), 0.02, epsilon = 1e-6);
+        approx::assert_relative_eq!(Topology::Walls.distance(from, to), 0.98, epsilon = 1e-6);
+        approx::assert_relative_eq!(Topology::Plane.distance(from, to), 0.98, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn confine() {
+        let position = na::Point2::new(1.25, -0.25);
+
+        assert_eq!(Topology::Torus.confine(position), na::Point2::new(0.25, 0.75));
+        assert_eq!(Topology::Walls.confine(position), na::Point2::new(1.0, 0.0));
+        assert_eq!(Topology::Plane.confine(position), position);
+    }
+}
@@ -4,6 +4,16 @@ use crate::*;
 pub struct World {
     pub(crate) animals: Vec<Animal>,
     pub(crate) foods: Vec<Food>,
+    /// неподвижные препятствия (config.world_obstacles)
+    pub(crate) obstacles: Vec<Obstacle>,
+    /// места роста еды
+    pub(crate) food_patches: FoodPatches,
+    /// сетка для поиска еды рядом с птичкой
+    pub(crate) food_grid: SpatialGrid,
+    /// сетка для поиска птичек рядом с птичкой
+    pub(crate) animal_grid: SpatialGrid,
+    /// номер для следующей новой птички
+    pub(crate) next_id: u64,
 }
 
 impl World {
@@ -14,18 +24,62 @@ impl World {
     pub fn foods(&self) -> &[Food] {
         &self.foods
     }
+
+    pub fn obstacles(&self) -> &[Obstacle] {
+        &self.obstacles
+    }
+
+    pub fn food_patches(&self) -> &FoodPatches {
+        &self.food_patches
+    }
 }
 
 impl World {
-    pub(crate) fn random(config: &Config, rng: &mut dyn RngCore) -> Self {
-        let animals = (0..config.world_animals)
-            .map(|_| Animal::random(config, rng))
-            .collect();
+    pub(crate) fn random(config: &Config, species: &[SpeciesConfig], rng: &mut dyn RngCore) -> Self {
+        //птички всех видов подряд, номера общие
+        let mut animals: Vec<Animal> = Vec::new();
+        for (k, species) in species.iter().enumerate() {
+            for _ in 0..species.config.world_animals {
+                let id = AnimalId(animals.len() as u64);
+                let mut animal = Animal::random(&species.config, rng, id);
+                animal.species = k;
+                animals.push(animal);
+            }
+        }
+        let next_id = animals.len() as u64;
 
+        let food_patches = FoodPatches::random(config, rng);
         let foods = (0..config.world_foods)
-            .map(|_| Food::random(rng))
+            .map(|_| Food::random(config, &food_patches, rng))
             .collect();
 
-        Self { animals, foods }
+        Self::new(config, animals, foods, food_patches, next_id)
+    }
+
+    pub(crate) fn new(config: &Config,
+                      animals: Vec<Animal>,
+                      foods: Vec<Food>,
+                      food_patches: FoodPatches,
+                      next_id: u64) -> Self {
+        //ячейка не меньше дальности зрения и размера еды, чтобы хватало соседних ячеек
+        let cell_size = config.eye_fov_range.max(config.food_size);
+        let mut food_grid = SpatialGrid::new(cell_size);
+        food_grid.rebuild(foods.iter().map(|f| f.position));
+
+        Self {
+            animals,
+            foods,
+            obstacles: config.world_obstacles.clone(),
+            food_patches,
+            food_grid,
+            animal_grid: SpatialGrid::new(cell_size),
+            next_id,
+        }
+    }
+    /// Новый постоянный номер птички
+    pub(crate) fn new_id(&mut self) -> AnimalId {
+        let id = AnimalId(self.next_id);
+        self.next_id += 1;
+        id
     }
 }